@@ -0,0 +1,214 @@
+use std::mem::swap;
+use crate::grid::FixedGrid;
+
+/// How cells outside the grid are seen by the rule.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Edge<T> {
+    /// There is nothing outside, so edge cells have fewer neighbours.
+    Bounded,
+    /// The grid repeats, so the right edge neighbours the left one.
+    Wrapping,
+    /// The grid is surrounded by an infinite sea of this value. The rule is applied to the
+    /// background too, so it can change between steps.
+    Background(T),
+}
+
+/// The 3x3 block around a cell in row-major order, with the cell itself in the middle. Cells
+/// outside a bounded grid are `None`.
+#[derive(Clone, Copy, Debug)]
+pub struct Neighbourhood<T> {
+    cells: [Option<T>; 9],
+}
+
+impl<T> Neighbourhood<T> where T: Copy + PartialEq {
+    /// Get the cell at the offset, where both are in -1..=1.
+    pub fn get(&self, dx: isize, dy: isize) -> Option<T> {
+        self.cells[((dy + 1) * 3 + (dx + 1)) as usize]
+    }
+
+    pub fn center(&self) -> T {
+        self.cells[4].unwrap()
+    }
+
+    /// All nine cells including the center, for rules that read the block as a pattern.
+    pub fn cells(&self) -> &[Option<T>; 9] {
+        &self.cells
+    }
+
+    /// The up to eight surrounding cells.
+    pub fn neighbours(&self) -> impl Iterator<Item=T> + '_ {
+        self.cells.iter().enumerate()
+            .filter(|(i, _)| *i != 4)
+            .filter_map(|(_, v)| *v)
+    }
+
+    /// The up to four surrounding cells that share a side.
+    pub fn cardinal(&self) -> impl Iterator<Item=T> + '_ {
+        [1, 3, 5, 7].iter().filter_map(move |i| self.cells[*i])
+    }
+
+    /// Count the surrounding cells with the value.
+    pub fn count(&self, v: T) -> usize {
+        self.neighbours().filter(|v2| *v2 == v).count()
+    }
+
+    /// Make a neighbourhood from cells in row-major order, where `None` is outside the grid.
+    pub fn new(cells: [Option<T>; 9]) -> Neighbourhood<T> {
+        Neighbourhood { cells }
+    }
+}
+
+/// Cellular automaton that keeps two buffers and swaps them after every step, so stepping
+/// does not allocate.
+///
+/// A step that moves things in several phases, like cucumbers moving east and then south,
+/// is done by calling `step` once per phase; each phase sees the result of the previous one.
+#[derive(Clone)]
+pub struct Automaton<T> {
+    current: FixedGrid<T>,
+    next: FixedGrid<T>,
+    edge: Edge<T>,
+}
+
+impl<T> Automaton<T> where T: Copy + PartialEq {
+    pub fn grid(&self) -> &FixedGrid<T> {
+        &self.current
+    }
+
+    pub fn into_grid(self) -> FixedGrid<T> {
+        self.current
+    }
+
+    /// Get the value of the cells outside the grid, if there are any.
+    pub fn background(&self) -> Option<T> {
+        match self.edge {
+            Edge::Background(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn neighbourhood(&self, x: usize, y: usize) -> Neighbourhood<T> {
+        let grid = &self.current;
+        let mut cells = [None; 9];
+
+        for (i, cell) in cells.iter_mut().enumerate() {
+            let x2 = (x + (i % 3)).wrapping_sub(1);
+            let y2 = (y + (i / 3)).wrapping_sub(1);
+
+            *cell = match self.edge {
+                Edge::Bounded => grid.get_safe(x2, y2).copied(),
+                Edge::Wrapping => Some(*grid.get_wrapping(x2.wrapping_add(grid.width()), y2.wrapping_add(grid.height()))),
+                Edge::Background(v) => Some(grid.get_safe(x2, y2).copied().unwrap_or(v)),
+            };
+        }
+
+        Neighbourhood { cells }
+    }
+
+    /// Apply the rule to every cell at once, and return whether anything changed.
+    pub fn step(&mut self, mut rule: impl FnMut(T, &Neighbourhood<T>) -> T) -> bool {
+        let mut changed = false;
+
+        for y in 0..self.current.height() {
+            for x in 0..self.current.width() {
+                let neighbourhood = self.neighbourhood(x, y);
+                let v = neighbourhood.center();
+                let new_v = rule(v, &neighbourhood);
+                if new_v != v {
+                    changed = true;
+                }
+
+                self.next.set(x, y, new_v);
+            }
+        }
+
+        if let Edge::Background(v) = self.edge {
+            let new_v = rule(v, &Neighbourhood { cells: [Some(v); 9] });
+            if new_v != v {
+                changed = true;
+            }
+
+            self.edge = Edge::Background(new_v);
+        }
+
+        swap(&mut self.current, &mut self.next);
+
+        changed
+    }
+
+    /// Step until nothing changes, and return the amount of steps taken including the last
+    /// one that changed nothing.
+    pub fn run_until_stable(&mut self, mut rule: impl FnMut(T, &Neighbourhood<T>) -> T) -> usize {
+        let mut steps = 1;
+        while self.step(&mut rule) {
+            steps += 1;
+        }
+
+        steps
+    }
+
+    pub fn new(grid: FixedGrid<T>, edge: Edge<T>) -> Automaton<T> {
+        Automaton {
+            next: grid.clone(),
+            current: grid,
+            edge,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn life(v: u8, n: &Neighbourhood<u8>) -> u8 {
+        match (v, n.count(b'#')) {
+            (b'#', 2) | (_, 3) => b'#',
+            _ => b'.',
+        }
+    }
+
+    #[test]
+    fn test_bounded() {
+        let grid = FixedGrid::<u8>::from_str(".....\n..#..\n..#..\n..#..\n.....\n");
+        let mut automaton = Automaton::new(grid.clone(), Edge::Bounded);
+
+        assert!(automaton.step(life));
+        assert_eq!(automaton.grid().line(2), b".###.");
+        assert!(automaton.step(life));
+        assert_eq!(automaton.grid().data(), grid.data());
+
+        let mut still = Automaton::new(FixedGrid::<u8>::from_str("##\n##\n"), Edge::Bounded);
+        assert_eq!(still.run_until_stable(life), 1);
+    }
+
+    #[test]
+    fn test_wrapping() {
+        // A blinker across the corner only survives if the edges wrap.
+        let grid = FixedGrid::<u8>::from_str("##..#\n.....\n.....\n.....\n.....\n");
+        let mut bounded = Automaton::new(grid.clone(), Edge::Bounded);
+        let mut wrapping = Automaton::new(grid, Edge::Wrapping);
+
+        bounded.step(life);
+        wrapping.step(life);
+
+        assert_eq!(bounded.grid().count(b'#'), 0);
+        assert_eq!(wrapping.grid().count(b'#'), 3);
+        assert_eq!(wrapping.grid().line(0), b"#....");
+        assert_eq!(wrapping.grid().line(1), b"#....");
+        assert_eq!(wrapping.grid().line(4), b"#....");
+    }
+
+    #[test]
+    fn test_background() {
+        // Empty cells with no live neighbours come alive, so the background blinks.
+        let mut automaton = Automaton::new(FixedGrid::new(3, 3, b'.'), Edge::Background(b'.'));
+        let rule = |v: u8, n: &Neighbourhood<u8>| if v == b'.' && n.count(b'#') == 0 { b'#' } else { b'.' };
+
+        assert!(automaton.step(rule));
+        assert_eq!(automaton.background(), Some(b'#'));
+        assert_eq!(automaton.grid().count(b'#'), 9);
+        assert!(automaton.step(rule));
+        assert_eq!(automaton.background(), Some(b'.'));
+        assert_eq!(automaton.neighbourhood(0, 0).get(-1, -1), Some(b'.'));
+    }
+}
@@ -0,0 +1,209 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use rustc_hash::FxHashMap;
+
+/// A repeating sequence of states. The state at step `start` is seen again at step
+/// `start + length`, where the initial state is step 0.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Cycle {
+    pub start: usize,
+    pub length: usize,
+}
+
+impl Cycle {
+    /// Get the step before the end of the first cycle that has the same state as step `n`.
+    pub fn equivalent_step(&self, n: usize) -> usize {
+        if n < self.start {
+            n
+        } else {
+            self.start + ((n - self.start) % self.length)
+        }
+    }
+
+    /// Get the value at step `n` from the values of the steps up to the end of the first
+    /// cycle, for values that repeat along with the state.
+    pub fn extrapolate<V: Clone>(&self, history: &[V], n: usize) -> V {
+        history[self.equivalent_step(n)].clone()
+    }
+
+    /// Get the value at step `n` from the values of the steps up to the end of the first
+    /// cycle, for values that grow by the same amount every cycle (like a height or a score).
+    pub fn extrapolate_growth(&self, history: &[i64], n: usize) -> i64 {
+        let step = self.equivalent_step(n);
+        if n < self.start {
+            return history[step];
+        }
+
+        let growth = history[self.start + self.length] - history[self.start];
+        let cycles = ((n - self.start) / self.length) as i64;
+
+        history[step] + (growth * cycles)
+    }
+}
+
+/// Find the cycle with Brent's algorithm. This needs no memory besides two states, but the
+/// step function is called up to three times the start plus length.
+pub fn brent<S: Clone + Eq>(initial: S, mut step: impl FnMut(&S) -> S) -> Cycle {
+    let mut power = 1;
+    let mut length = 1;
+    let mut tortoise = initial.clone();
+    let mut hare = step(&initial);
+
+    while tortoise != hare {
+        if power == length {
+            tortoise = hare.clone();
+            power *= 2;
+            length = 0;
+        }
+
+        hare = step(&hare);
+        length += 1;
+    }
+
+    let mut tortoise = initial.clone();
+    let mut hare = initial;
+    for _ in 0..length {
+        hare = step(&hare);
+    }
+
+    let mut start = 0;
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&hare);
+        start += 1;
+    }
+
+    Cycle { start, length }
+}
+
+/// Find the cycle with Floyd's algorithm. It calls the step function more often than Brent's,
+/// but finds the start before measuring the length.
+pub fn floyd<S: Clone + Eq>(initial: S, mut step: impl FnMut(&S) -> S) -> Cycle {
+    let mut tortoise = step(&initial);
+    let mut hare = step(&tortoise);
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        let half = step(&hare);
+        hare = step(&half);
+    }
+
+    let mut start = 0;
+    tortoise = initial;
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&hare);
+        start += 1;
+    }
+
+    let mut length = 1;
+    hare = step(&tortoise);
+    while tortoise != hare {
+        hare = step(&hare);
+        length += 1;
+    }
+
+    Cycle { start, length }
+}
+
+/// Detect cycles by remembering a hash of every state, which suits simulations that change a
+/// grid in place. Only the 64-bit hashes are stored, so a collision could in theory give a
+/// false cycle.
+#[derive(Clone, Default)]
+pub struct CycleDetector {
+    seen: FxHashMap<u64, usize>,
+    steps: usize,
+}
+
+impl CycleDetector {
+    /// Record the state of the next step, starting with the initial state as step 0. Returns
+    /// the cycle once a state has been seen before.
+    pub fn check<S: Hash>(&mut self, state: &S) -> Option<Cycle> {
+        let mut hasher = DefaultHasher::new();
+        state.hash(&mut hasher);
+        let step = self.steps;
+        self.steps += 1;
+
+        self.seen.insert(hasher.finish(), step)
+            .map(|start| Cycle { start, length: step - start })
+    }
+
+    /// Get the amount of states checked so far.
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    pub fn clear(&mut self) {
+        self.seen.clear();
+        self.steps = 0;
+    }
+
+    pub fn new() -> CycleDetector {
+        CycleDetector::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::automaton::{Automaton, Edge};
+    use crate::grid::FixedGrid;
+
+    fn next_number(n: &u32) -> u32 {
+        (n * n + 1) % 255
+    }
+
+    #[test]
+    fn test_brent_floyd() {
+        let mut detector = CycleDetector::new();
+        let mut n = 3u32;
+        let expected = loop {
+            if let Some(cycle) = detector.check(&n) {
+                break cycle;
+            }
+
+            n = next_number(&n);
+        };
+
+        assert_eq!(brent(3u32, next_number), expected);
+        assert_eq!(floyd(3u32, next_number), expected);
+        assert_eq!(brent(0u32, |n| (n + 1) % 7), Cycle { start: 0, length: 7 });
+        assert_eq!(floyd(0u32, |n| (n + 1) % 7), Cycle { start: 0, length: 7 });
+    }
+
+    #[test]
+    fn test_extrapolate() {
+        // 0, 1, 2, 3, 4, 2, 3, 4, ...
+        let cycle = brent(0u32, |n| if *n == 4 { 2 } else { n + 1 });
+        let history = [0, 1, 2, 3, 4, 2];
+
+        assert_eq!(cycle, Cycle { start: 2, length: 3 });
+        assert_eq!(cycle.equivalent_step(1), 1);
+        assert_eq!(cycle.extrapolate(&history, 1_000_000), 4);
+
+        // A total that goes up by 9 every cycle.
+        let totals = [0, 1, 3, 6, 10, 12];
+        assert_eq!(cycle.extrapolate_growth(&totals, 8), 12 + 3 + 4 + 2);
+        assert_eq!(cycle.extrapolate_growth(&totals, 1), 1);
+    }
+
+    #[test]
+    fn test_grid_detector() {
+        let grid = FixedGrid::<u8>::from_str(".....\n..#..\n..#..\n..#..\n.....\n");
+        let mut automaton = Automaton::new(grid, Edge::Bounded);
+        let mut detector = CycleDetector::new();
+
+        let cycle = loop {
+            if let Some(cycle) = detector.check(automaton.grid()) {
+                break cycle;
+            }
+
+            automaton.step(|v, n| match (v, n.count(b'#')) {
+                (b'#', 2) | (_, 3) => b'#',
+                _ => b'.',
+            });
+        };
+
+        assert_eq!(cycle, Cycle { start: 0, length: 2 });
+        assert_eq!(detector.steps(), 3);
+    }
+}
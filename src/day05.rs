@@ -1,12 +1,7 @@
-use std::cmp::max;
-use num::abs;
 use common::aoc::{print_result, run_many, print_time_cold};
-use common::grid::FixedGrid;
+use common::grid::{FixedGrid, LineKind};
 use common::parsers::{parse_u32s_amount};
 
-const RIGHT: (i32, i32) = (1, 0);
-const LEFT: (i32, i32) = (-1, 0);
-
 fn main() {
     let input = include_bytes!("../input/day05.txt");
 
@@ -24,32 +19,10 @@ fn main() {
 }
 
 fn puzzle(lines: &[Line], width: usize, height: usize, allow_diagonals: bool) -> usize {
+    let kind = if allow_diagonals { LineKind::AxisOrDiagonal } else { LineKind::Axis };
     let mut grid = FixedGrid::new(width, height, 0);
-    for Line{source: (sx, sy), dir: (dx, dy), len} in lines.iter() {
-        if !allow_diagonals && (*dx != 0 && *dy != 0) {
-            continue
-        }
-
-        if (*dx, *dy) == RIGHT {
-            let slice = grid.get_slice_mut(*sx as usize,*sx as usize + len + 1, *sy as usize);
-            for v in slice {
-                *v += 1;
-            }
-        } else if (*dx, *dy) == LEFT {
-            let slice = grid.get_slice_mut(*sx as usize - len, *sx as usize + 1, *sy as usize);
-            for v in slice {
-                *v += 1;
-            }
-        }else {
-            let mut x = *sx;
-            let mut y = *sy;
-            for _ in 0..=*len {
-                *grid.get_mut(x as usize, y as usize).unwrap() += 1;
-
-                x += dx;
-                y += dy;
-            }
-        }
+    for Line{from, to} in lines.iter() {
+        grid.draw_line_kind(*from, *to, kind, |v| *v += 1);
     }
 
     grid.data().iter().filter(|v| **v > 1).count()
@@ -70,17 +43,15 @@ fn parse_input(input: &[u8]) -> (Vec<Line>, (usize, usize)) {
         }
 
         let (x1, y1, x2, y2) = (
-            current_line[0] as i32,
-            current_line[1] as i32,
-            current_line[2] as i32,
-            current_line[3] as i32
+            current_line[0] as usize,
+            current_line[1] as usize,
+            current_line[2] as usize,
+            current_line[3] as usize
         );
-        let dist = max(abs(x2 - x1), abs(y2 - y1));
 
         res.push(Line{
-            source: (x1, y1),
-            dir: ((x2-x1)/dist, (y2-y1)/dist),
-            len: dist as usize,
+            from: (x1, y1),
+            to: (x2, y2),
         });
 
         if x1 > max_x {
@@ -97,14 +68,13 @@ fn parse_input(input: &[u8]) -> (Vec<Line>, (usize, usize)) {
         }
     }
 
-    (res, ((max_x+1) as usize, (max_y+1) as usize))
+    (res, (max_x+1, max_y+1))
 }
 
 #[derive(Debug)]
 struct Line {
-    source: (i32, i32),
-    dir: (i32, i32),
-    len: usize,
+    from: (usize, usize),
+    to: (usize, usize),
 }
 
 #[cfg(test)]
@@ -1,5 +1,6 @@
 use common::aoc::{print_result, run_many, print_time_cold};
 use common::parsers::{parse_u32_list};
+use common::prefix::PrefixSum;
 
 fn main() {
     let input = include_bytes!("../input/day07.txt");
@@ -17,43 +18,54 @@ fn main() {
     print_time_cold("Total", dur_p + dur_p1 + dur_p2, dur_pc + dur_p1c + dur_p2c);
 }
 
-fn puzzle(input: &[u32], fuel_cb: impl Fn(u32) -> u32) -> u32 {
-    let max = *input.iter().max().unwrap();
-    let mut winning_fuel = u32::MAX;
-
-    for i in 0..max {
-        let mut fuel = 0;
-        for crab in input.iter().cloned() {
-            let diff = if crab > i {
-                crab - i
-            } else {
-                i - crab
-            };
-
-            fuel += fuel_cb(diff);
-            if fuel > winning_fuel {
-                break
-            }
-        }
-
-        if fuel < winning_fuel {
-            winning_fuel = fuel;
-        } else {
-            break;
-        }
-    }
+/// Fuel costs of moving every crab to each position, as (linear, triangular) pairs. With the
+/// crabs sorted, the distances to one position are sums over the crabs left and right of it.
+fn costs(input: &[u32]) -> impl Iterator<Item=(i64, i64)> {
+    let mut crabs: Vec<i64> = input.iter().map(|c| *c as i64).collect();
+    crabs.sort_unstable();
+    let squares: Vec<i64> = crabs.iter().map(|c| c * c).collect();
+
+    let n = crabs.len() as i64;
+    let max = crabs.last().copied().unwrap_or(0);
+    let sums = PrefixSum::new(&crabs);
+    let square_sums = PrefixSum::new(&squares);
+
+    (0..=max).map(move |pos| {
+        let k = crabs.partition_point(|c| *c < pos);
+        let left = pos * k as i64 - sums.prefix(k);
+        let right = sums.suffix(k) - pos * (n - k as i64);
+        let linear = left + right;
+        let squared = square_sums.total() - 2 * pos * sums.total() + n * pos * pos;
 
-    winning_fuel
+        (linear, (squared + linear) / 2)
+    })
 }
 
 fn part1(input: &[u32]) -> u32 {
-    puzzle(input, |diff| diff)
+    costs(input).map(|(linear, _)| linear).min().unwrap() as u32
 }
 
 fn part2(input: &[u32]) -> u32 {
-    puzzle(input, |diff| (diff * (diff + 1)) / 2)
+    costs(input).map(|(_, triangular)| triangular).min().unwrap() as u32
 }
 
 fn parse_input(input: &[u8]) -> Vec<u32> {
     parse_u32_list(input)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &[u32] = &[16, 1, 2, 0, 4, 2, 7, 1, 2, 14];
+
+    #[test]
+    fn test_part1() {
+        assert_eq!(part1(SAMPLE), 37);
+    }
+
+    #[test]
+    fn test_part2() {
+        assert_eq!(part2(SAMPLE), 168);
+    }
+}
@@ -1,5 +1,5 @@
 use common::aoc::{print_result, run_many, print_time_cold};
-use common::grid::{valid_offsets, TinyGrid};
+use common::grid::{Grid, TinyGrid};
 
 fn main() {
     let input = include_bytes!("../input/day11.txt");
@@ -19,13 +19,12 @@ fn puzzle<const W: usize, const S: usize>(input: &TinyGrid<u8, W, S>) -> (usize,
     let mut stack = Vec::with_capacity(64);
     let mut grid = *input;
     let mut total_flashes = 0;
-    let h = S / W;
 
     for n in 1.. {
         let mut flashes = 0;
 
-        for y in 0..h {
-            for x in 0..W {
+        for y in 0..grid.height() {
+            for x in 0..grid.width() {
                 let v = grid.get_mut(x, y).unwrap();
                 *v += 1;
                 if *v > 9 {
@@ -38,7 +37,7 @@ fn puzzle<const W: usize, const S: usize>(input: &TinyGrid<u8, W, S>) -> (usize,
             grid.set(x, y, 0);
             flashes += 1;
 
-            for (x2, y2) in valid_offsets(true, x, y, W, h) {
+            for (x2, y2) in grid.neighbours(x, y, true) {
                 let v = grid.get_mut(x2, y2).unwrap();
                 if *v > 0 && *v < 10 {
                     *v += 1;
@@ -62,12 +61,7 @@ fn puzzle<const W: usize, const S: usize>(input: &TinyGrid<u8, W, S>) -> (usize,
 }
 
 fn parse_input<const W: usize, const S: usize>(input: &[u8]) -> TinyGrid<u8, W, S> {
-    let mut arr = [0u8; S];
-    for (i, v) in input.iter().filter(|v| **v != b'\n').map(|v| *v - b'0').enumerate() {
-        arr[i] = v;
-    }
-
-    TinyGrid::new(arr)
+    TinyGrid::parse_digits(input)
 }
 
 #[cfg(test)]
@@ -1,5 +1,6 @@
 use common::aoc::{print_result, run_many, print_time_cold};
-use common::grid::FixedGrid;
+use common::grid::{FixedGrid, SparseGrid};
+use common::ocr;
 use common::parser;
 
 fn main() {
@@ -10,8 +11,13 @@ fn main() {
     let (res_p2, dur_p2, dur_p2c) = run_many(100, || part2(&points, &folds));
 
     print_result("P1", res_p1);
-    println!("Result (P2):");
-    res_p2.print();
+    match ocr::read(&res_p2, b'#') {
+        Ok(text) => print_result("P2", text),
+        Err(err) => {
+            println!("Result (P2): {}", err);
+            res_p2.print();
+        }
+    }
 
     print_time_cold("Parse", dur_p, dur_pc);
     print_time_cold("P1", dur_p1, dur_p1c);
@@ -24,60 +30,33 @@ fn part1(points: &[(u32, u32)], folds: &[Fold]) -> usize {
 }
 
 fn part2(points: &[(u32, u32)], folds: &[Fold]) -> FixedGrid<u8> {
-    let points = perform_folds(points, folds, None);
-    let mut max_x = 0;
-    let mut max_y = 0;
-    for (x, y) in points.iter().cloned() {
-        if x > max_x {
-            max_x = x;
-        }
-        if y > max_y {
-            max_y = y;
-        }
-    }
-
-    let mut grid = FixedGrid::new((max_x+1) as usize, (max_y+1) as usize, b' ');
-    for (x, y) in points.iter() {
-        grid[(*x as usize, *y as usize)] = b'#';
-    }
-
-    grid
+    perform_folds(points, folds, None).to_grid(b' ')
 }
 
-fn perform_folds(points: &[(u32, u32)], mut folds: &[Fold], fold_amount: Option<usize>) -> Vec<(u32, u32)> {
-    let mut points = points.to_vec();
+fn perform_folds(points: &[(u32, u32)], mut folds: &[Fold], fold_amount: Option<usize>) -> SparseGrid<u8> {
     if let Some(fold_amount) = fold_amount {
         folds = &folds[..fold_amount];
     }
 
-    for fold in folds.iter() {
-        match fold {
-            Fold::X(fold_x) => {
-                for (x, _) in points.iter_mut() {
-                    if *x > *fold_x {
-                        *x -= (*x - *fold_x) * 2;
-                    }
-                }
-            }
-            Fold::Y(fold_y) => {
-                for (_, y) in points.iter_mut() {
-                    if *y > *fold_y {
-                        *y -= (*y - *fold_y) * 2;
-                    }
+    points.iter()
+        .map(|(x, y)| {
+            let (mut x, mut y) = (*x as isize, *y as isize);
+            for fold in folds.iter() {
+                match fold {
+                    Fold::X(fold_x) if x > *fold_x => x -= (x - *fold_x) * 2,
+                    Fold::Y(fold_y) if y > *fold_y => y -= (y - *fold_y) * 2,
+                    _ => {}
                 }
             }
-        }
-    }
-
-    points.sort_unstable();
-    points.dedup();
 
-    points
+            ((x, y), b'#')
+        })
+        .collect()
 }
 
 enum Fold {
-    X(u32),
-    Y(u32),
+    X(isize),
+    Y(isize),
 }
 
 fn parse_point_line(input: &[u8]) -> Option<((u32, u32), &[u8])> {
@@ -93,8 +72,9 @@ fn parse_fold_line(input: &[u8]) -> Option<(Fold, &[u8])> {
     let (_, input) = parser::expect_bytes(input, b"fold along ")?;
     let (axis, input) = parser::byte(input)?;
     let (_, input) = parser::expect_byte(input, b'=')?;
-    let (pos, input) = parser::uint(input)?;
+    let (pos, input) = parser::uint::<u32>(input)?;
     let (_, input) = parser::rest_of_line(input)?;
+    let pos = pos as isize;
 
     Some((match axis {
         b'x' => Fold::X(pos),
@@ -121,3 +101,47 @@ fn parse_input(input: &[u8]) -> (Vec<(u32, u32)>, Vec<Fold>) {
 
     (points, folds)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &[u8] = b"6,10
+0,14
+9,10
+0,3
+10,4
+4,11
+6,0
+6,12
+4,1
+0,13
+10,12
+3,4
+3,0
+8,4
+1,10
+2,14
+8,10
+9,0
+
+fold along y=7
+fold along x=5
+";
+
+    #[test]
+    fn test_part1() {
+        let (points, folds) = parse_input(EXAMPLE);
+        assert_eq!(part1(&points, &folds), 17);
+    }
+
+    #[test]
+    fn test_part2() {
+        let (points, folds) = parse_input(EXAMPLE);
+        let grid = part2(&points, &folds);
+
+        assert_eq!((grid.width(), grid.height()), (5, 5));
+        assert_eq!(grid.count(b'#'), 16);
+        assert_eq!(grid.line(2), b"#   #");
+    }
+}
@@ -1,5 +1,5 @@
 use common::aoc::{print_result, run_many, print_time_cold};
-use common::grid::{FixedGrid, Dijkstra, DijkstraStep};
+use common::grid::{FixedGrid, Dijkstra, DijkstraStep, SearchGrid, TiledGrid};
 
 fn main() {
     let input = include_bytes!("../input/day15.txt");
@@ -17,46 +17,29 @@ fn main() {
     print_time_cold("Total", dur_p + dur_p1 + dur_p2, dur_pc + dur_p1c + dur_p2c);
 }
 
-fn checker<'a>(target: &'a (usize, usize)) -> impl Fn(&'a i64, (usize, usize)) -> DijkstraStep {
-    |v, pos| {
-        if pos == *target {
-            DijkstraStep::Found(*v)
-        } else {
-            DijkstraStep::Continue(*v, 0)
-        }
+fn step(target: (usize, usize), v: i64, pos: (usize, usize)) -> DijkstraStep {
+    if pos == target {
+        DijkstraStep::Found(v)
+    } else {
+        DijkstraStep::Continue(v, 0)
     }
 }
 
 fn part1(input: &FixedGrid<i64>) -> i64 {
     let mut dijkstra = Dijkstra::new(false, 0, 0, 0);
     let target = (input.width()-1, input.height()-1);
-    dijkstra.run(input, checker(&target));
+    dijkstra.run(input, |v, pos| step(target, *v, pos));
 
     dijkstra.found_cost().unwrap()
 }
 
 fn part2(input: &FixedGrid<i64>) -> i64 {
-    let mut big_grid = FixedGrid::new(input.width() * 5, input.height() * 5, 0);
-    for y in 0..big_grid.height() {
-        let sy = y % input.height();
-        let my = (y / input.height()) as i64;
-
-        for x in 0..big_grid.width() {
-            let sx = x % input.width();
-            let mx = (x / input.width()) as i64;
-
-            let mut v = input[(sx, sy)] + (mx + my);
-            if v >= 10 {
-                v -= 9;
-            }
-
-            big_grid.set(x, y, v);
-        }
-    }
+    let big_grid = TiledGrid::new(input, 5, 5, |v, tx, ty| (v + tx as i64 + ty as i64 - 1) % 9 + 1);
 
     let mut dijkstra = Dijkstra::new(false, 0, 0, 0);
-    let target = (big_grid.width()-1, big_grid.height()-1);
-    dijkstra.run(&big_grid, checker(&target));
+    let (width, height) = big_grid.size();
+    let target = (width - 1, height - 1);
+    dijkstra.run(&big_grid, |v, pos| step(target, v, pos));
 
     dijkstra.found_cost().unwrap()
 }
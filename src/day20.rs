@@ -1,19 +1,7 @@
 use common::aoc::{print_result, run_many, print_time_cold};
 use common::parser;
 use common::grid::FixedGrid;
-use std::mem::swap;
-
-const OFFSETS: [(usize, usize); 9] = [
-    (!0, !0),
-    (0, !0),
-    (1, !0),
-    (!0, 0),
-    (0, 0),
-    (1, 0),
-    (!0, 1),
-    (0, 1),
-    (1, 1),
-];
+use common::automaton::{Automaton, Edge};
 
 fn main() {
     let input = include_bytes!("../input/day20.txt");
@@ -34,53 +22,34 @@ fn main() {
 fn puzzle(enhancement: &[u8; 512], initial: &FixedGrid<u8>, steps: usize) -> usize {
     let width = initial.width() + ((steps) * 2);
     let height = initial.height() + ((steps) * 2);
-    let mut curr_grid = FixedGrid::new(width, height, b'.');
-    let mut next_grid = FixedGrid::new(width, height, b'.');
-    let mut oob = false;
+    let mut grid = FixedGrid::new(width, height, b'.');
+    grid.blit(initial, steps, steps);
 
-    curr_grid.blit(initial, steps, steps);
+    // The infinite sea beyond our bounds is inverted by the rule when the 0th enhancement is '#'.
+    let mut automaton = Automaton::new(grid, Edge::Background(b'.'));
 
     // Print the grid when testing.
     #[cfg(test)] {
         println!();
-        curr_grid.print();
+        automaton.grid().print();
     }
 
     for _ in 0..steps {
-        // Perform search
-        for y in 0..curr_grid.width() {
-            for x in 0..curr_grid.height() {
-                let mut index = 0;
-                for (xo, yo) in OFFSETS.iter() {
-                    let x = x.wrapping_add(*xo);
-                    let y = y.wrapping_add(*yo);
-
-                    index <<= 1;
-                    if curr_grid.has_oob(x, y, b'#', oob) {
-                        index |= 1;
-                    }
-                }
-
-                next_grid.set(x, y, enhancement[index]);
-            }
-        }
-
-        // Lazy copy next_grid to curr_grid.
-        swap(&mut curr_grid, &mut next_grid);
+        automaton.step(|_, neighbourhood| {
+            let index = neighbourhood.cells().iter()
+                .fold(0, |index, v| (index << 1) | (*v == Some(b'#')) as usize);
 
-        // For cases where the 0th enhancement is '#', invert the infinite sea beyond our bounds.
-        if enhancement[0] == b'#' {
-            oob = !oob;
-        }
+            enhancement[index]
+        });
 
         // Testing: show the grid
         #[cfg(test)] {
             println!();
-            curr_grid.print();
+            automaton.grid().print();
         }
     }
 
-    curr_grid.data().iter().filter(|v| **v == b'#').count()
+    automaton.grid().count(b'#')
 }
 
 fn parse_input(input: &[u8]) -> ([u8; 512], FixedGrid<u8>) {
@@ -1,5 +1,6 @@
 use common::aoc::{print_result, run_many, print_time_cold};
 use common::grid::FixedGrid;
+use common::automaton::{Automaton, Edge};
 
 fn main() {
     let input = include_bytes!("../input/day25.txt");
@@ -14,36 +15,24 @@ fn main() {
     print_time_cold("Total", dur_p + dur_p1, dur_pc + dur_p1c);
 }
 
-fn part1(input: &FixedGrid<Space>) -> u32 {
-    let mut current = input.clone();
-    let mut moves = Vec::with_capacity(64);
+fn part1(input: &FixedGrid<Space>) -> usize {
+    let mut automaton = Automaton::new(input.clone(), Edge::Wrapping);
 
     for n in 1.. {
-        for (x, y, v) in current.iter() {
-            if let Space::EastCucumber = v {
-                if let Space::Free = current.get_wrapping(x + 1, y) {
-                    moves.push((x, y));
-                }
+        let any_moved_right = automaton.step(|v, neighbourhood| {
+            match v {
+                Space::Free if neighbourhood.get(-1, 0) == Some(Space::EastCucumber) => Space::EastCucumber,
+                Space::EastCucumber if neighbourhood.get(1, 0) == Some(Space::Free) => Space::Free,
+                _ => v,
             }
-        }
-
-        let any_moved_right = !moves.is_empty();
-        for (x, y) in moves.drain(0..) {
-            current.swap_wrapping((x, y), (x + 1, y));
-        }
-
-        for (x, y, v) in current.iter() {
-            if let Space::SouthCucumber = v {
-                if let Space::Free = current.get_wrapping(x, y + 1) {
-                    moves.push((x, y));
-                }
+        });
+        let any_moved_down = automaton.step(|v, neighbourhood| {
+            match v {
+                Space::Free if neighbourhood.get(0, -1) == Some(Space::SouthCucumber) => Space::SouthCucumber,
+                Space::SouthCucumber if neighbourhood.get(0, 1) == Some(Space::Free) => Space::Free,
+                _ => v,
             }
-        }
-
-        let any_moved_down = !moves.is_empty();
-        for (x, y) in moves.drain(0..) {
-            current.swap_wrapping((x, y), (x, y + 1));
-        }
+        });
 
         if !any_moved_down && !any_moved_right {
             return n;
@@ -64,9 +53,30 @@ fn parse_input(input: &[u8]) -> FixedGrid<Space> {
     })
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Eq, PartialEq)]
 enum Space {
     EastCucumber,
     SouthCucumber,
     Free,
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &[u8] = b"v...>>.vv>
+.vv>>.vv..
+>>.>v>...v
+>>v>>.>.v.
+v>v.vv.v..
+>.>>..v...
+.vv..>.>v.
+v.v..>>v.v
+....v..v.>
+";
+
+    #[test]
+    fn test_part1() {
+        assert_eq!(part1(&parse_input(EXAMPLE)), 58);
+    }
+}
@@ -0,0 +1,293 @@
+use std::ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+use num::{NumCast, Signed};
+use crate::grid::FixedGrid;
+
+/// Point or vector on a plane where y grows downwards, like in the grids.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Point2<T> {
+    pub x: T,
+    pub y: T,
+}
+
+impl<T> Point2<T> {
+    pub const fn new(x: T, y: T) -> Point2<T> {
+        Point2 { x, y }
+    }
+}
+
+impl<T> Point2<T> where T: Signed + Copy + Ord {
+    /// Distance to the origin when moving along the axes.
+    pub fn manhattan(&self) -> T {
+        self.x.abs() + self.y.abs()
+    }
+
+    pub fn manhattan_distance(&self, other: &Point2<T>) -> T {
+        (*self - *other).manhattan()
+    }
+
+    /// Distance when diagonal moves are allowed, like a king on a chess board.
+    pub fn chebyshev_distance(&self, other: &Point2<T>) -> T {
+        let diff = *self - *other;
+        diff.x.abs().max(diff.y.abs())
+    }
+
+    /// Get the vector with every axis clamped to -1..=1, which is a single step towards it.
+    pub fn signum(&self) -> Point2<T> {
+        Point2::new(self.x.signum(), self.y.signum())
+    }
+
+    /// Rotate a quarter turn counter-clockwise around the origin, as seen on the screen.
+    pub fn rotate_left(&self) -> Point2<T> {
+        Point2::new(self.y, -self.x)
+    }
+
+    /// Rotate a quarter turn clockwise around the origin, as seen on the screen.
+    pub fn rotate_right(&self) -> Point2<T> {
+        Point2::new(-self.y, self.x)
+    }
+
+    pub fn step(&self, direction: Direction) -> Point2<T> {
+        *self + direction.offset()
+    }
+
+    pub fn neighbours(&self, diagonal: bool) -> impl Iterator<Item=Point2<T>> + '_ {
+        let directions: &[Direction] = if diagonal { &Direction::ALL } else { &Direction::CARDINAL };
+        directions.iter().map(move |d| self.step(*d))
+    }
+}
+
+impl<T> Point2<T> where T: NumCast + Copy {
+    /// Get the grid position of this point, if it is inside the grid.
+    pub fn to_grid_pos<V>(&self, grid: &FixedGrid<V>) -> Option<(usize, usize)> {
+        let x: usize = num::cast(self.x)?;
+        let y: usize = num::cast(self.y)?;
+
+        if x < grid.width() && y < grid.height() {
+            Some((x, y))
+        } else {
+            None
+        }
+    }
+
+    /// Get the point of a grid position, if it fits in `T`.
+    pub fn from_grid_pos((x, y): (usize, usize)) -> Option<Point2<T>> {
+        Some(Point2::new(num::cast(x)?, num::cast(y)?))
+    }
+}
+
+impl<T> From<(T, T)> for Point2<T> {
+    fn from((x, y): (T, T)) -> Self {
+        Point2::new(x, y)
+    }
+}
+
+impl<T> From<Point2<T>> for (T, T) {
+    fn from(p: Point2<T>) -> Self {
+        (p.x, p.y)
+    }
+}
+
+impl<T: Add<Output=T>> Add for Point2<T> {
+    type Output = Point2<T>;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Point2::new(self.x + rhs.x, self.y + rhs.y)
+    }
+}
+
+impl<T: Sub<Output=T>> Sub for Point2<T> {
+    type Output = Point2<T>;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Point2::new(self.x - rhs.x, self.y - rhs.y)
+    }
+}
+
+impl<T: Mul<Output=T> + Copy> Mul<T> for Point2<T> {
+    type Output = Point2<T>;
+
+    fn mul(self, rhs: T) -> Self::Output {
+        Point2::new(self.x * rhs, self.y * rhs)
+    }
+}
+
+impl<T: Neg<Output=T>> Neg for Point2<T> {
+    type Output = Point2<T>;
+
+    fn neg(self) -> Self::Output {
+        Point2::new(-self.x, -self.y)
+    }
+}
+
+impl<T: AddAssign> AddAssign for Point2<T> {
+    fn add_assign(&mut self, rhs: Self) {
+        self.x += rhs.x;
+        self.y += rhs.y;
+    }
+}
+
+impl<T: SubAssign> SubAssign for Point2<T> {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.x -= rhs.x;
+        self.y -= rhs.y;
+    }
+}
+
+impl<T: MulAssign + Copy> MulAssign<T> for Point2<T> {
+    fn mul_assign(&mut self, rhs: T) {
+        self.x *= rhs;
+        self.y *= rhs;
+    }
+}
+
+impl<T: Signed + Copy + Ord> Add<Direction> for Point2<T> {
+    type Output = Point2<T>;
+
+    fn add(self, rhs: Direction) -> Self::Output {
+        self + rhs.offset()
+    }
+}
+
+/// Compass direction on the screen, so north is up and has a negative y offset.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Direction {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
+impl Direction {
+    pub const CARDINAL: [Direction; 4] = [Direction::North, Direction::East, Direction::South, Direction::West];
+    pub const ALL: [Direction; 8] = [
+        Direction::North,
+        Direction::NorthEast,
+        Direction::East,
+        Direction::SouthEast,
+        Direction::South,
+        Direction::SouthWest,
+        Direction::West,
+        Direction::NorthWest,
+    ];
+
+    pub fn offset<T: Signed>(&self) -> Point2<T> {
+        let (x, y) = match self {
+            Direction::North => (0, -1),
+            Direction::NorthEast => (1, -1),
+            Direction::East => (1, 0),
+            Direction::SouthEast => (1, 1),
+            Direction::South => (0, 1),
+            Direction::SouthWest => (-1, 1),
+            Direction::West => (-1, 0),
+            Direction::NorthWest => (-1, -1),
+        };
+
+        Point2::new(signed(x), signed(y))
+    }
+
+    /// Turn by 45 degree steps, where positive is clockwise.
+    pub fn rotate(&self, steps: i32) -> Direction {
+        Direction::ALL[(*self as i32 + steps).rem_euclid(8) as usize]
+    }
+
+    pub fn turn_left(&self) -> Direction {
+        self.rotate(-2)
+    }
+
+    pub fn turn_right(&self) -> Direction {
+        self.rotate(2)
+    }
+
+    pub fn turn_around(&self) -> Direction {
+        self.rotate(4)
+    }
+
+    pub fn is_diagonal(&self) -> bool {
+        (*self as usize) % 2 == 1
+    }
+
+    /// Parse the cardinal directions from `NESW`, `URDL` or `^>v<`.
+    pub fn from_byte(b: u8) -> Option<Direction> {
+        match b {
+            b'N' | b'U' | b'^' => Some(Direction::North),
+            b'E' | b'R' | b'>' => Some(Direction::East),
+            b'S' | b'D' | b'v' => Some(Direction::South),
+            b'W' | b'L' | b'<' => Some(Direction::West),
+            _ => None,
+        }
+    }
+}
+
+fn signed<T: Signed>(v: i8) -> T {
+    match v {
+        -1 => -T::one(),
+        1 => T::one(),
+        _ => T::zero(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_point_math() {
+        let a = Point2::new(3i64, -4);
+        let b = Point2::new(-1i64, 2);
+
+        assert_eq!(a + b, Point2::new(2, -2));
+        assert_eq!(a - b, Point2::new(4, -6));
+        assert_eq!(-a * 2, Point2::new(-6, 8));
+        assert_eq!(a.manhattan(), 7);
+        assert_eq!(a.manhattan_distance(&b), 10);
+        assert_eq!(a.chebyshev_distance(&b), 6);
+        assert_eq!(a.signum(), Point2::new(1, -1));
+
+        let mut c = a;
+        c += b;
+        c -= Point2::new(2, 0);
+        c *= 3;
+        assert_eq!(c, Point2::new(0, -6));
+
+        // A waypoint east of the ship ends up north of it after turning left.
+        assert_eq!(Point2::new(10i32, 0).rotate_left(), Point2::new(0, -10));
+        assert_eq!(Point2::new(10i32, 0).rotate_right(), Point2::new(0, 10));
+        assert_eq!(a.neighbours(false).count(), 4);
+        assert!(a.neighbours(true).all(|p| p.chebyshev_distance(&a) == 1));
+    }
+
+    #[test]
+    fn test_direction() {
+        assert_eq!(Direction::North.turn_right(), Direction::East);
+        assert_eq!(Direction::North.turn_left(), Direction::West);
+        assert_eq!(Direction::NorthEast.turn_around(), Direction::SouthWest);
+        assert_eq!(Direction::West.rotate(1), Direction::NorthWest);
+        assert!(Direction::SouthEast.is_diagonal());
+        assert!(!Direction::South.is_diagonal());
+
+        let pos = Point2::new(0i32, 0);
+        let walked = b"^^>>v<".iter()
+            .map(|b| Direction::from_byte(*b).unwrap())
+            .fold(pos, |pos, d| pos + d);
+        assert_eq!(walked, Point2::new(1, -1));
+        assert_eq!(Direction::from_byte(b'x'), None);
+    }
+
+    #[test]
+    fn test_grid_pos() {
+        let grid = FixedGrid::new(4, 3, 0u8);
+
+        assert_eq!(Point2::new(3i32, 2).to_grid_pos(&grid), Some((3, 2)));
+        assert_eq!(Point2::new(4i32, 2).to_grid_pos(&grid), None);
+        assert_eq!(Point2::new(0i32, -1).to_grid_pos(&grid), None);
+        assert_eq!(Point2::<i64>::from_grid_pos((3, 2)), Some(Point2::new(3, 2)));
+        assert_eq!(Point2::<i8>::from_grid_pos((300, 2)), None);
+
+        let walked = Point2::new(0i32, 0).step(Direction::North).to_grid_pos(&grid);
+        assert_eq!(walked, None);
+    }
+}
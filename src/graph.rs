@@ -0,0 +1,478 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+use std::fmt::{Display, Write};
+use std::hash::Hash;
+use rustc_hash::FxHashMap;
+use smallvec::SmallVec;
+
+/// A graph with named nodes and weighted edges. Nodes are interned on first use, and the
+/// algorithms work with their indices. In an undirected graph, every edge is stored in both
+/// directions.
+#[derive(Clone)]
+pub struct Graph<N, E = i64> {
+    directed: bool,
+    names: Vec<N>,
+    indices: FxHashMap<N, usize>,
+    exits: Vec<SmallVec<[(usize, E); 4]>>,
+    edges: Vec<(usize, usize, E)>,
+}
+
+impl<N, E> Graph<N, E> where N: Hash + Eq + Clone, E: Copy {
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn is_directed(&self) -> bool {
+        self.directed
+    }
+
+    pub fn name(&self, index: usize) -> &N {
+        &self.names[index]
+    }
+
+    pub fn index(&self, name: &N) -> Option<usize> {
+        self.indices.get(name).copied()
+    }
+
+    /// Get the index of the node, adding it if it's new.
+    pub fn add_node(&mut self, name: N) -> usize {
+        if let Some(index) = self.indices.get(&name) {
+            return *index;
+        }
+
+        let index = self.names.len();
+        self.names.push(name.clone());
+        self.indices.insert(name, index);
+        self.exits.push(SmallVec::new());
+
+        index
+    }
+
+    /// Add an edge between two nodes, adding the nodes if they're new. It goes both ways if the
+    /// graph is undirected.
+    pub fn add_edge(&mut self, from: N, to: N, weight: E) -> (usize, usize) {
+        let from = self.add_node(from);
+        let to = self.add_node(to);
+        self.add_edge_index(from, to, weight);
+
+        (from, to)
+    }
+
+    pub fn add_edge_index(&mut self, from: usize, to: usize, weight: E) {
+        self.exits[from].push((to, weight));
+        if !self.directed && from != to {
+            self.exits[to].push((from, weight));
+        }
+
+        self.edges.push((from, to, weight));
+    }
+
+    /// Get the edges going out from a node as target index and weight.
+    pub fn exits(&self, index: usize) -> &[(usize, E)] {
+        &self.exits[index]
+    }
+
+    /// Get every edge in the order they were added. Undirected edges are only listed once.
+    pub fn edges(&self) -> &[(usize, usize, E)] {
+        &self.edges
+    }
+
+    /// Get the nodes reachable from the start in depth-first pre-order.
+    pub fn dfs(&self, start: usize) -> Vec<usize> {
+        let mut visited = vec![false; self.len()];
+        let mut order = Vec::with_capacity(self.len());
+        let mut stack = Vec::with_capacity(32);
+        stack.push(start);
+
+        while let Some(index) = stack.pop() {
+            if visited[index] {
+                continue;
+            }
+
+            visited[index] = true;
+            order.push(index);
+
+            for (next, _) in self.exits[index].iter().rev() {
+                if !visited[*next] {
+                    stack.push(*next);
+                }
+            }
+        }
+
+        order
+    }
+
+    /// Get the nodes reachable from the start in breadth-first order, along with the amount of
+    /// edges to get there.
+    pub fn bfs(&self, start: usize) -> Vec<(usize, usize)> {
+        let mut visited = vec![false; self.len()];
+        let mut order = Vec::with_capacity(self.len());
+        let mut queue = VecDeque::with_capacity(32);
+        visited[start] = true;
+        queue.push_back((start, 0));
+
+        while let Some((index, depth)) = queue.pop_front() {
+            order.push((index, depth));
+
+            for (next, _) in self.exits[index].iter() {
+                if !visited[*next] {
+                    visited[*next] = true;
+                    queue.push_back((*next, depth + 1));
+                }
+            }
+        }
+
+        order
+    }
+
+    /// Order the nodes so that every edge goes from an earlier to a later node. Ties are broken
+    /// by the lowest index. This returns `None` if there are any cycles.
+    pub fn topological_sort(&self) -> Option<Vec<usize>> {
+        let mut in_degree = vec![0usize; self.len()];
+        for exits in self.exits.iter() {
+            for (to, _) in exits.iter() {
+                in_degree[*to] += 1;
+            }
+        }
+
+        let mut ready: BinaryHeap<Reverse<usize>> = (0..self.len())
+            .filter(|i| in_degree[*i] == 0)
+            .map(Reverse)
+            .collect();
+        let mut order = Vec::with_capacity(self.len());
+
+        while let Some(Reverse(index)) = ready.pop() {
+            order.push(index);
+
+            for (to, _) in self.exits[index].iter() {
+                in_degree[*to] -= 1;
+                if in_degree[*to] == 0 {
+                    ready.push(Reverse(*to));
+                }
+            }
+        }
+
+        if order.len() == self.len() {
+            Some(order)
+        } else {
+            None
+        }
+    }
+
+    /// Find the strongly connected components with Tarjan's algorithm. They are listed in
+    /// reverse topological order. In an undirected graph, these are the connected components.
+    pub fn strongly_connected_components(&self) -> Vec<Vec<usize>> {
+        let mut indices = vec![usize::MAX; self.len()];
+        let mut low_links = vec![0usize; self.len()];
+        let mut on_stack = vec![false; self.len()];
+        let mut stack = Vec::with_capacity(self.len());
+        let mut call_stack: Vec<(usize, usize)> = Vec::with_capacity(32);
+        let mut components = Vec::new();
+        let mut next_index = 0;
+
+        for root in 0..self.len() {
+            if indices[root] != usize::MAX {
+                continue;
+            }
+
+            indices[root] = next_index;
+            low_links[root] = next_index;
+            next_index += 1;
+            stack.push(root);
+            on_stack[root] = true;
+            call_stack.push((root, 0));
+
+            while let Some(&(index, exit_index)) = call_stack.last() {
+                if let Some((next, _)) = self.exits[index].get(exit_index) {
+                    let next = *next;
+                    call_stack.last_mut().unwrap().1 += 1;
+
+                    if indices[next] == usize::MAX {
+                        indices[next] = next_index;
+                        low_links[next] = next_index;
+                        next_index += 1;
+                        stack.push(next);
+                        on_stack[next] = true;
+                        call_stack.push((next, 0));
+                    } else if on_stack[next] {
+                        low_links[index] = low_links[index].min(indices[next]);
+                    }
+                } else {
+                    call_stack.pop();
+                    if let Some(&(parent, _)) = call_stack.last() {
+                        low_links[parent] = low_links[parent].min(low_links[index]);
+                    }
+
+                    if low_links[index] == indices[index] {
+                        let mut component = Vec::new();
+                        while let Some(member) = stack.pop() {
+                            on_stack[member] = false;
+                            component.push(member);
+                            if member == index {
+                                break;
+                            }
+                        }
+
+                        components.push(component);
+                    }
+                }
+            }
+        }
+
+        components
+    }
+
+    /// Check if the graph has a cycle. For an undirected graph, going back and forth along the
+    /// same edge does not count, but parallel edges and self-loops do.
+    pub fn has_cycle(&self) -> bool {
+        if self.directed {
+            return self.topological_sort().is_none();
+        }
+
+        let mut roots: Vec<usize> = (0..self.len()).collect();
+        fn find_root(roots: &mut [usize], mut index: usize) -> usize {
+            while roots[index] != index {
+                roots[index] = roots[roots[index]];
+                index = roots[index];
+            }
+
+            index
+        }
+
+        for (from, to, _) in self.edges.iter() {
+            let a = find_root(&mut roots, *from);
+            let b = find_root(&mut roots, *to);
+            if a == b {
+                return true;
+            }
+
+            roots[a] = b;
+        }
+
+        false
+    }
+
+    /// Count the paths from one node to another. The limit callback gives how many times each
+    /// node may be visited, and `extra_visits` is a shared allowance for going past that limit
+    /// on nodes with a non-zero limit. Paths never return to the start, and end as soon as
+    /// they reach the target.
+    pub fn count_paths(&self, from: usize, to: usize, limit: impl Fn(usize) -> usize, extra_visits: usize) -> usize {
+        let mut count = 0;
+        self.for_each_path(from, to, limit, extra_visits, |_| count += 1);
+
+        count
+    }
+
+    /// Same as `count_paths`, but collect every path's nodes including both ends.
+    pub fn paths(&self, from: usize, to: usize, limit: impl Fn(usize) -> usize, extra_visits: usize) -> Vec<Vec<usize>> {
+        let mut paths = Vec::new();
+        self.for_each_path(from, to, limit, extra_visits, |path| paths.push(path.to_vec()));
+
+        paths
+    }
+
+    fn for_each_path(&self, from: usize, to: usize, limit: impl Fn(usize) -> usize, extra_visits: usize, mut cb: impl FnMut(&[usize])) {
+        let mut visits = vec![0usize; self.len()];
+        let mut path = Vec::with_capacity(32);
+        visits[from] = usize::MAX;
+        path.push(from);
+
+        self.visit_paths(to, &limit, extra_visits, &mut visits, &mut path, &mut cb);
+    }
+
+    fn visit_paths(&self, to: usize, limit: &impl Fn(usize) -> usize, extra_visits: usize, visits: &mut [usize], path: &mut Vec<usize>, cb: &mut impl FnMut(&[usize])) {
+        let index = *path.last().unwrap();
+
+        for (next, _) in self.exits[index].iter() {
+            let next = *next;
+            if next == to {
+                path.push(next);
+                cb(path);
+                path.pop();
+                continue;
+            }
+
+            let next_limit = limit(next);
+            let extra_visits = if visits[next] < next_limit {
+                extra_visits
+            } else if visits[next] != usize::MAX && next_limit > 0 && extra_visits > 0 {
+                extra_visits - 1
+            } else {
+                continue;
+            };
+
+            visits[next] += 1;
+            path.push(next);
+            self.visit_paths(to, limit, extra_visits, visits, path, cb);
+            path.pop();
+            visits[next] -= 1;
+        }
+    }
+
+    pub fn new(directed: bool) -> Graph<N, E> {
+        Graph {
+            directed,
+            names: Vec::new(),
+            indices: FxHashMap::default(),
+            exits: Vec::new(),
+            edges: Vec::new(),
+        }
+    }
+}
+
+impl<N, E> Graph<N, E> where N: Hash + Eq + Clone + Display, E: Copy + Display {
+    /// Render the graph in the Graphviz DOT format, with the weights as edge labels.
+    pub fn to_dot(&self) -> String {
+        let (kind, arrow) = if self.directed { ("digraph", "->") } else { ("graph", "--") };
+        let mut res = String::with_capacity(64 + self.edges.len() * 16);
+
+        writeln!(res, "{} {{", kind).unwrap();
+        for (index, name) in self.names.iter().enumerate() {
+            writeln!(res, "    n{} [label=\"{}\"];", index, escape_dot(name)).unwrap();
+        }
+        for (from, to, weight) in self.edges.iter() {
+            writeln!(res, "    n{} {} n{} [label=\"{}\"];", from, arrow, to, escape_dot(weight)).unwrap();
+        }
+        res.push_str("}\n");
+
+        res
+    }
+}
+
+/// Escape the quotes and backslashes of a label, so it can go in a quoted DOT string.
+fn escape_dot(label: impl Display) -> String {
+    let mut res = String::new();
+    for c in label.to_string().chars() {
+        if c == '"' || c == '\\' {
+            res.push('\\');
+        }
+        res.push(c);
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CAVES: &str = "start-A
+start-b
+A-c
+A-b
+b-d
+A-end
+b-end";
+
+    fn parse_caves(input: &str) -> Graph<&str> {
+        let mut graph = Graph::new(false);
+        for line in input.lines() {
+            let (left, right) = line.split_once('-').unwrap();
+            graph.add_edge(left, right, 1);
+        }
+
+        graph
+    }
+
+    #[test]
+    fn test_count_paths() {
+        let graph = parse_caves(CAVES);
+        let start = graph.index(&"start").unwrap();
+        let end = graph.index(&"end").unwrap();
+        let limit = |i| if graph.name(i).chars().next().unwrap().is_uppercase() { usize::MAX } else { 1 };
+
+        assert_eq!(graph.count_paths(start, end, limit, 0), 10);
+        assert_eq!(graph.count_paths(start, end, limit, 1), 36);
+
+        let paths = graph.paths(start, end, limit, 0);
+        assert_eq!(paths.len(), 10);
+        assert!(paths.iter().all(|p| p[0] == start && p[p.len() - 1] == end));
+    }
+
+    #[test]
+    fn test_traversal() {
+        let graph = parse_caves(CAVES);
+        let start = graph.index(&"start").unwrap();
+
+        let dfs: Vec<&str> = graph.dfs(start).into_iter().map(|i| *graph.name(i)).collect();
+        assert_eq!(dfs, vec!["start", "A", "c", "b", "d", "end"]);
+
+        let bfs: Vec<(&str, usize)> = graph.bfs(start).into_iter().map(|(i, d)| (*graph.name(i), d)).collect();
+        assert_eq!(bfs, vec![("start", 0), ("A", 1), ("b", 1), ("c", 2), ("end", 2), ("d", 2)]);
+    }
+
+    #[test]
+    fn test_topological_sort() {
+        let mut graph = Graph::<char>::new(true);
+        graph.add_edge('C', 'A', 1);
+        graph.add_edge('C', 'F', 1);
+        graph.add_edge('A', 'B', 1);
+        graph.add_edge('A', 'D', 1);
+        graph.add_edge('B', 'E', 1);
+        graph.add_edge('D', 'E', 1);
+        graph.add_edge('F', 'E', 1);
+
+        let order: String = graph.topological_sort().unwrap().into_iter().map(|i| *graph.name(i)).collect();
+        assert_eq!(order, "CAFBDE");
+        assert!(!graph.has_cycle());
+
+        graph.add_edge('E', 'C', 1);
+        assert!(graph.topological_sort().is_none());
+        assert!(graph.has_cycle());
+
+        let mut graph = Graph::<usize>::new(true);
+        for i in 0..4 {
+            graph.add_node(i);
+        }
+        graph.add_edge(1, 0, 1);
+        graph.add_edge(2, 3, 1);
+        assert_eq!(graph.topological_sort(), Some(vec![1, 0, 2, 3]));
+    }
+
+    #[test]
+    fn test_strongly_connected_components() {
+        let mut graph = Graph::<u32>::new(true);
+        for (a, b) in [(1, 2), (2, 3), (3, 1), (3, 4), (4, 5), (5, 4), (6, 6)] {
+            graph.add_edge(a, b, 1);
+        }
+
+        let mut components: Vec<Vec<u32>> = graph.strongly_connected_components().into_iter()
+            .map(|c| {
+                let mut names: Vec<u32> = c.into_iter().map(|i| *graph.name(i)).collect();
+                names.sort_unstable();
+                names
+            })
+            .collect();
+        assert_eq!(components[0], vec![4, 5]);
+        components.sort();
+        assert_eq!(components, vec![vec![1, 2, 3], vec![4, 5], vec![6]]);
+    }
+
+    #[test]
+    fn test_undirected_cycle() {
+        let mut graph = Graph::<u32>::new(false);
+        graph.add_edge(1, 2, 1);
+        graph.add_edge(2, 3, 1);
+        assert!(!graph.has_cycle());
+
+        graph.add_edge(3, 1, 1);
+        assert!(graph.has_cycle());
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let mut graph = Graph::<&str>::new(true);
+        graph.add_edge("a", "b", 5);
+
+        assert_eq!(graph.to_dot(), "digraph {\n    n0 [label=\"a\"];\n    n1 [label=\"b\"];\n    n0 -> n1 [label=\"5\"];\n}\n");
+
+        let mut graph = Graph::<&str>::new(false);
+        graph.add_edge("say \"hi\"", "C:\\", 1);
+        assert!(graph.to_dot().contains("n0 [label=\"say \\\"hi\\\"\"];"));
+        assert!(graph.to_dot().contains("n1 [label=\"C:\\\\\"];"));
+    }
+}
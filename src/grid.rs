@@ -1,13 +1,23 @@
 use std::cmp::Ordering;
-use std::collections::{VecDeque};
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::collections::{BinaryHeap, VecDeque};
 use std::ops::{Index, IndexMut};
 use radix_heap::{Radix, RadixHeapMap};
+use rustc_hash::FxHashMap;
+use smallvec::SmallVec;
+use crate::graph::Graph;
 
+/// Grid over signed positions made of equally sized chunks, which are added as cells are set.
+/// Cells in missing chunks have the default value. As a `Grid`, position (0, 0) is the top
+/// left corner of the top left chunk.
 #[derive(Clone)]
 pub struct GridSet<T> {
     chunk_width: isize,
     chunk_height: isize,
     grids: Vec<(FixedGrid<T>, isize, isize)>,
+    chunk_bounds: Option<((isize, isize), (isize, isize))>,
+    default: T,
 }
 
 impl<T> GridSet<T>
@@ -15,10 +25,7 @@ impl<T> GridSet<T>
         T: Clone + Copy,
 {
     pub fn chunk_index(&self, x: isize, y: isize) -> (isize, isize) {
-        let cx = if x < 0 { x - self.chunk_width - 1 } else { x } / self.chunk_width;
-        let cy = if y < 0 { y - self.chunk_width - 1 } else { y } / self.chunk_height;
-
-        (cx, cy)
+        (x.div_euclid(self.chunk_width), y.div_euclid(self.chunk_height))
     }
 
     pub fn chunk(&self, ix: isize, iy: isize) -> Option<(&FixedGrid<T>, isize, isize)> {
@@ -26,9 +33,203 @@ impl<T> GridSet<T>
             .find(|(_, ix2, iy2)| *ix2 == ix && *iy2 == iy)
             .map(|(g, ix2, iy2)| (g, ix2 * self.chunk_width, iy2 * self.chunk_height))
     }
+
+    /// Get the signed position of the cell at (0, 0).
+    pub fn origin(&self) -> (isize, isize) {
+        match self.chunk_bounds {
+            Some(((cx, cy), _)) => (cx * self.chunk_width, cy * self.chunk_height),
+            None => (0, 0),
+        }
+    }
+
+    pub fn get_signed(&self, x: isize, y: isize) -> &T {
+        let (cx, cy) = self.chunk_index(x, y);
+        match self.chunk(cx, cy) {
+            Some((grid, ox, oy)) => &grid[((x - ox) as usize, (y - oy) as usize)],
+            None => &self.default,
+        }
+    }
+
+    pub fn get_signed_mut(&mut self, x: isize, y: isize) -> &mut T {
+        let (cx, cy) = self.chunk_index(x, y);
+        let index = match self.grids.iter().position(|(_, ix, iy)| *ix == cx && *iy == cy) {
+            Some(index) => index,
+            None => self.add_chunk(cx, cy),
+        };
+
+        let (grid, ix, iy) = &mut self.grids[index];
+        let (ox, oy) = (*ix * self.chunk_width, *iy * self.chunk_height);
+        &mut grid[((x - ox) as usize, (y - oy) as usize)]
+    }
+
+    pub fn set_signed(&mut self, x: isize, y: isize, v: T) {
+        *self.get_signed_mut(x, y) = v;
+    }
+
+    fn add_chunk(&mut self, cx: isize, cy: isize) -> usize {
+        let grid = FixedGrid::new(self.chunk_width as usize, self.chunk_height as usize, self.default);
+        self.grids.push((grid, cx, cy));
+        self.chunk_bounds = match self.chunk_bounds {
+            Some(((x1, y1), (x2, y2))) => Some(((x1.min(cx), y1.min(cy)), (x2.max(cx), y2.max(cy)))),
+            None => Some(((cx, cy), (cx, cy))),
+        };
+
+        self.grids.len() - 1
+    }
+
+    pub fn new(chunk_width: usize, chunk_height: usize, default: T) -> GridSet<T> {
+        GridSet {
+            chunk_width: chunk_width as isize,
+            chunk_height: chunk_height as isize,
+            grids: Vec::new(),
+            chunk_bounds: None,
+            default,
+        }
+    }
 }
 
-#[derive(Clone)]
+/// Grid over signed positions that only stores the cells that are set, for points that are
+/// too far apart or too far from the origin for a `FixedGrid`. The bounding box grows as cells
+/// are inserted, but removing cells does not shrink it until `shrink_bounds` is called.
+#[derive(Clone, Debug)]
+pub struct SparseGrid<T> {
+    cells: FxHashMap<(isize, isize), T>,
+    bounds: Option<((isize, isize), (isize, isize))>,
+}
+
+impl<T> SparseGrid<T> {
+    pub fn get(&self, x: isize, y: isize) -> Option<&T> {
+        self.cells.get(&(x, y))
+    }
+
+    pub fn get_mut(&mut self, x: isize, y: isize) -> Option<&mut T> {
+        self.cells.get_mut(&(x, y))
+    }
+
+    /// Get the cell, inserting `v` first if it is not set.
+    pub fn get_or_insert(&mut self, x: isize, y: isize, v: T) -> &mut T {
+        self.grow_bounds(x, y);
+        self.cells.entry((x, y)).or_insert(v)
+    }
+
+    pub fn contains(&self, x: isize, y: isize) -> bool {
+        self.cells.contains_key(&(x, y))
+    }
+
+    /// Set the cell, and return the previous value.
+    pub fn insert(&mut self, x: isize, y: isize, v: T) -> Option<T> {
+        self.grow_bounds(x, y);
+        self.cells.insert((x, y), v)
+    }
+
+    pub fn remove(&mut self, x: isize, y: isize) -> Option<T> {
+        self.cells.remove(&(x, y))
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    /// Get the lowest and highest corner of the bounding box, both inclusive.
+    pub fn bounds(&self) -> Option<((isize, isize), (isize, isize))> {
+        self.bounds
+    }
+
+    /// Get the width and height of the bounding box.
+    pub fn size(&self) -> (usize, usize) {
+        match self.bounds {
+            Some(((x1, y1), (x2, y2))) => ((x2 - x1 + 1) as usize, (y2 - y1 + 1) as usize),
+            None => (0, 0),
+        }
+    }
+
+    /// Recompute the bounding box from the cells that are left.
+    pub fn shrink_bounds(&mut self) {
+        self.bounds = None;
+        let positions: Vec<(isize, isize)> = self.cells.keys().copied().collect();
+        for (x, y) in positions {
+            self.grow_bounds(x, y);
+        }
+    }
+
+    /// Iterate over the set cells, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item=(isize, isize, &T)> {
+        self.cells.iter().map(|((x, y), v)| (*x, *y, v))
+    }
+
+    pub fn count(&self, v: T) -> usize where T: PartialEq {
+        self.cells.values().filter(|v2| **v2 == v).count()
+    }
+
+    /// Make a dense grid over the bounding box, where position (0, 0) is its lowest corner.
+    pub fn to_grid(&self, empty: T) -> FixedGrid<T> where T: Copy {
+        let (width, height) = self.size();
+        let mut grid = FixedGrid::new(width, height, empty);
+        if let Some(((x1, y1), _)) = self.bounds {
+            for ((x, y), v) in self.cells.iter() {
+                grid[((x - x1) as usize, (y - y1) as usize)] = *v;
+            }
+        }
+
+        grid
+    }
+
+    fn grow_bounds(&mut self, x: isize, y: isize) {
+        self.bounds = match self.bounds {
+            Some(((x1, y1), (x2, y2))) => Some(((x1.min(x), y1.min(y)), (x2.max(x), y2.max(y)))),
+            None => Some(((x, y), (x, y))),
+        };
+    }
+
+    pub fn new() -> SparseGrid<T> {
+        SparseGrid {
+            cells: FxHashMap::default(),
+            bounds: None,
+        }
+    }
+}
+
+impl<T> Default for SparseGrid<T> {
+    fn default() -> Self {
+        SparseGrid::new()
+    }
+}
+
+impl<T> FromIterator<((isize, isize), T)> for SparseGrid<T> {
+    fn from_iter<I: IntoIterator<Item=((isize, isize), T)>>(iter: I) -> Self {
+        let mut grid = SparseGrid::new();
+        for ((x, y), v) in iter {
+            grid.insert(x, y, v);
+        }
+
+        grid
+    }
+}
+
+/// Render the bounding box, with `.` for the cells that are not set.
+impl<T> Display for SparseGrid<T> where T: Display {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let Some(((x1, y1), (x2, y2))) = self.bounds {
+            for y in y1..=y2 {
+                for x in x1..=x2 {
+                    match self.get(x, y) {
+                        Some(v) => write!(f, "{}", v)?,
+                        None => write!(f, ".")?,
+                    }
+                }
+                writeln!(f)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Eq, Hash, PartialEq)]
 pub struct FixedGrid<T> {
     data: Vec<T>,
     width: usize,
@@ -226,18 +427,16 @@ impl<T> FixedGrid<T>
         }
     }
 
+    /// Parse a grid with one tile per byte, panicking if the lines are not all as wide. Blank
+    /// lines inside the grid count as too short, so they panic too. Use `GridParser` to handle
+    /// that as an error or to pad short lines.
     pub fn parse_bytes(data: &[u8], cb: impl Fn(u8) -> T) -> FixedGrid<T> {
-        let width = data.iter().take_while(|b| **b != b'\n').count();
-        let data: Vec<T> = data.iter().filter(|b| **b != b'\n').map(|v| cb(*v)).collect();
-
-        Self::from(width, data.len() / width, data)
+        GridParser::new().parse_bytes(data, cb).unwrap_or_else(|e| panic!("{}", e))
     }
 
+    /// Same as `parse_bytes`, but with one tile per char.
     pub fn parse_str(data: &str, cb: impl Fn(char) -> T) -> FixedGrid<T> {
-        let width = data.chars().take_while(|b| *b != '\n').count();
-        let data: Vec<T> = data.chars().map(|v| cb(v)).collect();
-
-        Self::from(width, data.len() / width, data)
+        GridParser::new().parse_str(data, cb).unwrap_or_else(|e| panic!("{}", e))
     }
 }
 
@@ -279,245 +478,1653 @@ impl<T> FixedGrid<T>
     }
 }
 
-impl<T> Index<(usize, usize)> for FixedGrid<T> {
-    type Output = T;
+/// Positions of the marker bytes found while parsing a grid, in reading order.
+pub type Markers = FxHashMap<u8, Vec<(usize, usize)>>;
 
-    fn index(&self, idx: (usize, usize)) -> &Self::Output {
-        self.get(idx.0, idx.1).unwrap()
-    }
+/// Problems found while parsing a grid. Lines and columns start at 1 so they can be looked up
+/// in an editor.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum GridParseError {
+    UnknownTile { line: usize, column: usize, tile: char },
+    RaggedLine { line: usize, width: usize, expected: usize },
 }
 
-impl<T> IndexMut<(usize, usize)> for FixedGrid<T> {
-    fn index_mut(&mut self, idx: (usize, usize)) -> &mut Self::Output {
-        self.get_mut(idx.0, idx.1).unwrap()
+impl Display for GridParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            GridParseError::UnknownTile { line, column, tile } => {
+                write!(f, "unknown tile {:?} at line {}, column {}", tile, line, column)
+            }
+            GridParseError::RaggedLine { line, width, expected } => {
+                write!(f, "line {} is {} wide, expected {}", line, width, expected)
+            }
+        }
     }
 }
 
-impl FixedGrid<u8>
+impl Error for GridParseError {}
+
+/// Validating parser that the grid constructors are built on. Lines may end with `\n` or
+/// `\r\n`, and empty lines before and after the grid are ignored. Lines that are not as wide
+/// as the first are an error, unless padding is enabled. That includes empty lines inside the
+/// grid, which are not skipped.
+#[derive(Clone, Copy)]
+pub struct GridParser<T> {
+    fill: Option<T>,
+}
+
+impl<T> GridParser<T>
+    where
+        T: Copy,
 {
-    #[allow(dead_code)]
-    pub fn print(&self) {
-        for y in 0..self.height {
-            for x in 0..self.width {
-                unsafe {
-                    print!("{}", *self.get_unchecked(x, y) as char);
-                }
-            }
+    pub fn parse_bytes(&self, data: &[u8], mut cb: impl FnMut(u8) -> T) -> Result<FixedGrid<T>, GridParseError> {
+        self.try_parse_bytes(data, |b, _, _| Some(cb(b)))
+    }
 
-            println!();
-        }
+    /// Parse with a callback that gets the position of each byte in the grid, and can reject
+    /// it by returning `None`.
+    pub fn try_parse_bytes(&self, data: &[u8], cb: impl FnMut(u8, usize, usize) -> Option<T>) -> Result<FixedGrid<T>, GridParseError> {
+        let lines = data.split(|b| *b == b'\n')
+            .map(|line| line.strip_suffix(b"\r").unwrap_or(line).iter().copied())
+            .collect();
+
+        self.parse_lines(lines, cb)
     }
 
-    pub fn from_str(s: &str) -> FixedGrid<u8> {
-        let width = s.lines().filter(|l| !l.is_empty()).next().unwrap().len();
-        let height = s.lines().filter(|l| !l.is_empty()).count();
-        let data = s.bytes().filter(|c| *c != b'\n' && *c != b'\r').collect();
+    pub fn parse_str(&self, data: &str, mut cb: impl FnMut(char) -> T) -> Result<FixedGrid<T>, GridParseError> {
+        let lines = data.split('\n')
+            .map(|line| line.strip_suffix('\r').unwrap_or(line).chars())
+            .collect();
 
-        FixedGrid::from(width, height, data)
+        self.parse_lines(lines, |c, _, _| Some(cb(c)))
     }
-}
 
-impl FixedGrid<char>
-{
-    #[allow(dead_code)]
-    pub fn print(&self) {
-        for y in 0..self.height {
-            for x in 0..self.width {
-                unsafe {
-                    print!("{}", self.get_unchecked(x, y));
-                }
+    fn parse_lines<L, U>(&self, mut lines: Vec<L>, mut cb: impl FnMut(U, usize, usize) -> Option<T>) -> Result<FixedGrid<T>, GridParseError>
+        where
+            L: Iterator<Item=U> + Clone,
+            U: Copy + Into<char>,
+    {
+        while lines.last().map(|line| line.clone().next().is_none()).unwrap_or(false) {
+            lines.pop();
+        }
+        let first = lines.iter().position(|line| line.clone().next().is_some()).unwrap_or(lines.len());
+        if first == lines.len() {
+            return Ok(FixedGrid::empty());
+        }
+
+        let widths: Vec<usize> = lines[first..].iter().map(|line| line.clone().count()).collect();
+        let width = match self.fill {
+            Some(_) => widths.iter().copied().max().unwrap(),
+            None => widths[0],
+        };
+
+        let mut data = Vec::with_capacity(width * widths.len());
+        for (y, line) in lines.drain(first..).enumerate() {
+            if widths[y] > width || (widths[y] < width && self.fill.is_none()) {
+                return Err(GridParseError::RaggedLine { line: first + y + 1, width: widths[y], expected: width });
             }
 
-            println!();
+            for (x, v) in line.enumerate() {
+                match cb(v, x, y) {
+                    Some(tile) => data.push(tile),
+                    None => return Err(GridParseError::UnknownTile { line: first + y + 1, column: x + 1, tile: v.into() }),
+                }
+            }
+            if let Some(fill) = self.fill {
+                data.extend((widths[y]..width).map(|_| fill));
+            }
         }
+
+        Ok(FixedGrid::from(width, widths.len(), data))
     }
 
-    pub fn from_str(s: &str) -> FixedGrid<char> {
-        let width = s.lines().filter(|l| !l.is_empty()).next().unwrap().len();
-        let height = s.lines().filter(|l| !l.is_empty()).count();
-        let data = s.chars().filter(|c| *c != '\n' && *c != '\r').collect();
+    /// Pad lines that are shorter than the longest one with this value instead of failing.
+    pub fn with_padding(mut self, fill: T) -> GridParser<T> {
+        self.fill = Some(fill);
+        self
+    }
 
-        FixedGrid::from(width, height, data)
+    pub fn new() -> GridParser<T> {
+        GridParser { fill: None }
     }
 }
 
-#[derive(Copy, Clone)]
-pub struct TinyGrid<T, const W: usize, const S: usize> {
-    data: [T; S],
+impl<T> Default for GridParser<T>
+    where
+        T: Copy,
+{
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl<T, const W: usize, const S: usize> TinyGrid<T, W, S> {
-    pub fn get(&self, x: usize, y: usize) -> Option<&T> {
-        if x < W {
-            self.data.get(y * W + x)
-        } else {
-            None
-        }
-    }
+impl<T> FixedGrid<T>
+    where
+        T: Copy + TryFrom<u8>,
+{
+    /// Parse a grid with one tile per byte. Bytes for which `is_marker` holds are not converted,
+    /// but have their positions returned and their tiles set to `floor`.
+    pub fn parse_tiles(input: &[u8], is_marker: impl Fn(u8) -> bool, floor: T) -> Result<(FixedGrid<T>, Markers), GridParseError> {
+        let mut markers = Markers::default();
+        let grid = GridParser::new().try_parse_bytes(input, |b, x, y| {
+            if is_marker(b) {
+                markers.entry(b).or_default().push((x, y));
+                Some(floor)
+            } else {
+                T::try_from(b).ok()
+            }
+        })?;
 
-    pub fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut T> {
-        if x < W {
-            self.data.get_mut(y * W + x)
-        } else {
-            None
-        }
+        Ok((grid, markers))
     }
+}
 
-    pub const fn new(data: [T; S]) -> TinyGrid<T, W, S> {
-        TinyGrid { data }
-    }
+/// Which lines `FixedGrid::draw_line_kind` accepts.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LineKind {
+    Any,
+    /// Horizontal and vertical lines.
+    Axis,
+    /// Horizontal, vertical and 45 degree lines.
+    AxisOrDiagonal,
 }
 
-impl<T, const W: usize, const S: usize> TinyGrid<T, W, S> where T: Copy {
-    pub fn set(&mut self, x: usize, y: usize, v: T) {
-        if x >= W {
-            panic!("Set out of bounds")
-        }
+impl LineKind {
+    pub fn accepts(&self, from: (usize, usize), to: (usize, usize)) -> bool {
+        let dx = abs_diff(from.0, to.0);
+        let dy = abs_diff(from.1, to.1);
 
-        *self.data.get_mut((y * W) + x).unwrap() = v;
+        match self {
+            LineKind::Any => true,
+            LineKind::Axis => dx == 0 || dy == 0,
+            LineKind::AxisOrDiagonal => dx == 0 || dy == 0 || dx == dy,
+        }
     }
 }
 
-const OFFSETS_CARDINAL: [(usize, usize); 4] = [
-    (0, !0),
-    (!0, 0),
-    (1, 0),
-    (0, 1),
-];
+impl<T> FixedGrid<T>
+{
+    /// Call `f` on every cell of the line between `from` and `to`, including both ends, using
+    /// Bresenham's algorithm. Cells outside the grid are skipped.
+    pub fn draw_line(&mut self, from: (usize, usize), to: (usize, usize), mut f: impl FnMut(&mut T)) {
+        self.line_cells(from, to, false, false, &mut f);
+    }
 
-const OFFSETS_DIAGONAL: [(usize, usize); 8] = [
-    (!0, !0),
-    (!0, 0),
-    (!0, 1),
-    (0, !0),
-    (0, 1),
-    (1, !0),
-    (1, 0),
-    (1, 1),
-];
+    /// Same as `draw_line`, but only if it is of the kind. Returns whether it was drawn.
+    pub fn draw_line_kind(&mut self, from: (usize, usize), to: (usize, usize), kind: LineKind, mut f: impl FnMut(&mut T)) -> bool {
+        if !kind.accepts(from, to) {
+            return false;
+        }
 
-#[derive(Debug)]
-pub enum BFSStep<S> {
-    Continue(S),
-    Found(S),
-    Warp(usize, usize, S),
-    WarpLevel(usize, usize, isize, S),
-    DeadEnd,
-}
+        self.line_cells(from, to, false, false, &mut f);
+        true
+    }
 
-/// BFS struct is to keep some state between runs to avoid needless allocations.
-#[derive(Clone)]
-pub struct BFS<S> {
-    visited: Vec<FixedGrid<bool>>,
-    queue: VecDeque<(usize, usize, usize, usize, S)>,
-    found_pos: Option<(usize, usize)>,
-}
+    /// Draw lines between each of the points. The joint between two consecutive lines is only
+    /// visited once, and so is the first point of a closed polyline that ends where it started.
+    /// Cells where other lines cross are visited once per line.
+    pub fn draw_polyline(&mut self, points: &[(usize, usize)], mut f: impl FnMut(&mut T)) {
+        if let [single] = points {
+            self.line_cells(*single, *single, false, false, &mut f);
+        }
 
-impl<S> BFS<S> where S: Clone + Default {
-    /// Get the position where it was found in the last run.
-    pub fn found_pos(&self) -> Option<(usize, usize)> {
-        self.found_pos
+        let closed = points.len() > 2 && points.first() == points.last();
+        let last = points.len().saturating_sub(2);
+        for (i, pair) in points.windows(2).enumerate() {
+            self.line_cells(pair[0], pair[1], i > 0, closed && i == last, &mut f);
+        }
     }
 
-    /// Run BFS on this grid. This will reset all state, so is safe to call multiple times.
-    pub fn run_multilevel<'a, T>(&'a mut self, grid: &'a FixedGrid<T>, start_x: usize, start_y: usize, start_level: usize, diagonal: bool, check: impl Fn(&'a T, (usize, usize), usize, &S) -> BFSStep<S>) -> Option<(&'a T, usize, S)> {
-        for i in 0..self.visited.len() {
-            self.visited[i].clear();
-        }
-        while self.visited.len() <= start_level {
-            self.visited.push(FixedGrid::empty());
+    /// Call `f` on every cell of the rectangle with the inclusive corners, or only on its
+    /// outline when not filled. Every cell is visited once.
+    pub fn draw_rect(&mut self, from: (usize, usize), to: (usize, usize), filled: bool, mut f: impl FnMut(&mut T)) {
+        let (left, right) = (from.0.min(to.0), from.0.max(to.0));
+        let (top, bottom) = (from.1.min(to.1), from.1.max(to.1));
+        if self.width == 0 || self.height == 0 {
+            return;
         }
-        if self.visited[start_level].width < grid.width || self.visited[start_level].height < grid.height {
-            self.visited[start_level] = FixedGrid::blank(grid.width, grid.height);
+
+        for y in top..=bottom.min(self.height - 1) {
+            for x in left..=right.min(self.width - 1) {
+                if filled || x == left || x == right || y == top || y == bottom {
+                    f(&mut self[(x, y)]);
+                }
+            }
         }
+    }
 
-        self.found_pos = None;
+    fn line_cells(&mut self, from: (usize, usize), to: (usize, usize), skip_first: bool, skip_last: bool, f: &mut impl FnMut(&mut T)) {
+        let (mut x, mut y) = (from.0 as isize, from.1 as isize);
+        let (x2, y2) = (to.0 as isize, to.1 as isize);
+        let dx = (x2 - x).abs();
+        let dy = -(y2 - y).abs();
+        let sx = (x2 - x).signum();
+        let sy = (y2 - y).signum();
+        let mut err = dx + dy;
+        let mut first = true;
+
+        loop {
+            let last = x == x2 && y == y2;
+            if !(first && skip_first) && !(last && skip_last) && x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
+                f(&mut self[(x as usize, y as usize)]);
+            }
+            if last {
+                break;
+            }
 
-        self.queue.clear();
-        self.queue.push_back((start_x, start_y, 0, start_level, S::default()));
+            first = false;
+            let e2 = err * 2;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+}
 
-        let offsets: &[(usize, usize)] = if diagonal { &OFFSETS_DIAGONAL } else { &OFFSETS_CARDINAL };
+impl<T> FixedGrid<T>
+{
+    /// Get all positions reachable from `start` through cardinal neighbours matching the
+    /// predicate. The result is empty if the start position itself does not match.
+    pub fn flood_fill(&self, start: (usize, usize), predicate: impl Fn(&T) -> bool) -> Vec<(usize, usize)> {
+        let mut visited = FixedGrid::new(self.width, self.height, false);
+        let mut stack = Vec::with_capacity(64);
+        let mut region = Vec::with_capacity(64);
 
-        while let Some((x, y, l, level, state)) = self.queue.pop_front() {
-            self.visited[level][(x, y)] = true;
-            let v = &grid[(x, y)];
+        self.fill_region(start, false, &predicate, &mut visited, &mut stack, |pos| region.push(pos));
 
-            match check(v, (x, y), level, &state) {
-                BFSStep::Continue(new_state) => {
-                    for (x_offset, y_offset) in offsets.iter() {
-                        let x2 = x.wrapping_add(*x_offset);
-                        if x2 >= grid.width() {
-                            continue;
-                        }
-                        let y2 = y.wrapping_add(*y_offset);
-                        if y2 >= grid.height() {
-                            continue;
-                        }
-                        if *self.visited[level].get(x2, y2).unwrap() {
-                            continue;
-                        }
+        region
+    }
 
-                        self.queue.push_back((x2, y2, l + 1, level, new_state.clone()));
-                    }
-                }
-                BFSStep::Found(new_state) => {
-                    self.found_pos = Some((x, y));
-                    return Some((v, l, new_state));
-                }
-                BFSStep::Warp(x, y, new_state) => {
-                    self.queue.push_front((x, y, l, level, new_state.clone()));
+    /// Label every connected region of cells matching the predicate. Labels in the returned
+    /// grid start at 1 and index into the component list as `label - 1`, while 0 marks cells
+    /// that are not part of any component.
+    pub fn label_components(&self, predicate: impl Fn(&T) -> bool, diagonal: bool) -> (FixedGrid<u32>, Vec<Component>) {
+        let mut labels = FixedGrid::new(self.width, self.height, 0u32);
+        let mut visited = FixedGrid::new(self.width, self.height, false);
+        let mut stack = Vec::with_capacity(64);
+        let mut components = Vec::new();
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if visited[(x, y)] || !predicate(&self[(x, y)]) {
+                    continue;
                 }
-                BFSStep::WarpLevel(x, y, level_change, new_state) => {
-                    if level_change < 0 && level == 0 {
-                        panic!("level underflow at len={}", l);
-                    }
-                    if level > 10000 {
-                        panic!("level overflow at len={}", l);
-                    }
 
-                    let new_level = (level as isize + level_change as isize) as usize;
-                    while self.visited.len() <= new_level {
-                        self.visited.push(FixedGrid::empty());
-                    }
-                    if self.visited[new_level].width < grid.width || self.visited[new_level].height < grid.height {
-                        self.visited[new_level] = FixedGrid::blank(grid.width, grid.height);
-                    }
+                let label = components.len() as u32 + 1;
+                let mut component = Component { size: 0, min: (x, y), max: (x, y) };
+                self.fill_region((x, y), diagonal, &predicate, &mut visited, &mut stack, |(x2, y2)| {
+                    labels[(x2, y2)] = label;
+                    component.size += 1;
+                    component.min = (component.min.0.min(x2), component.min.1.min(y2));
+                    component.max = (component.max.0.max(x2), component.max.1.max(y2));
+                });
 
-                    self.queue.push_front((x, y, l, new_level, new_state.clone()));
-                }
-                BFSStep::DeadEnd => {
-                    // Do nothing.
-                }
+                components.push(component);
             }
         }
 
-        None
+        (labels, components)
     }
 
-    pub fn run<'a, T>(&'a mut self, grid: &'a FixedGrid<T>, start_x: usize, start_y: usize, diagonal: bool, check: impl Fn(&'a T, (usize, usize), &S) -> BFSStep<S>) -> Option<(&'a T, usize, S)> {
-        self.run_multilevel(grid, start_x, start_y, 0, diagonal, |v, pos, _, state| {
-            check(v, pos, state)
-        })
-    }
+    fn fill_region(
+        &self,
+        start: (usize, usize),
+        diagonal: bool,
+        predicate: &impl Fn(&T) -> bool,
+        visited: &mut FixedGrid<bool>,
+        stack: &mut Vec<(usize, usize)>,
+        mut cb: impl FnMut((usize, usize)),
+    ) {
+        match self.get_safe(start.0, start.1) {
+            Some(v) if !visited[start] && predicate(v) => {}
+            _ => return,
+        }
+
+        stack.clear();
+        stack.push(start);
+        visited[start] = true;
+
+        while let Some((x, y)) = stack.pop() {
+            cb((x, y));
+
+            for pos in valid_offsets(diagonal, x, y, self.width, self.height) {
+                if !visited[pos] && predicate(&self[pos]) {
+                    visited[pos] = true;
+                    stack.push(pos);
+                }
+            }
+        }
+    }
+}
+
+/// A connected region found by `FixedGrid::label_components`. `min` and `max` are the inclusive
+/// corners of its bounding box.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Component {
+    pub size: usize,
+    pub min: (usize, usize),
+    pub max: (usize, usize),
+}
+
+impl<T> FixedGrid<T>
+{
+    /// Find the shortest distance between every pair of interesting tiles, along with the gated
+    /// tiles crossed on the way. Interesting and gated tiles are also passable. This runs one
+    /// BFS per interesting tile over the whole grid.
+    pub fn point_graph(&self, interesting: impl Fn(&T) -> bool, passable: impl Fn(&T) -> bool, gated: impl Fn(&T) -> bool) -> PointGraph {
+        let points: Vec<(usize, usize)> = self.iter()
+            .filter(|(_, _, v)| interesting(v))
+            .map(|(x, y, _)| (x, y))
+            .collect();
+        let mut point_indices = FixedGrid::new(self.width, self.height, usize::MAX);
+        for (i, pos) in points.iter().enumerate() {
+            point_indices[*pos] = i;
+        }
+
+        let mut distances = FixedGrid::new(self.width, self.height, usize::MAX);
+        let mut parents = FixedGrid::new(self.width, self.height, NO_PARENT_POS);
+        let mut queue = VecDeque::with_capacity(64);
+        let mut paths = Vec::with_capacity(points.len());
+
+        for (i, start) in points.iter().enumerate() {
+            let mut point_paths = Vec::new();
+            distances.data.fill(usize::MAX);
+            parents.data.fill(NO_PARENT_POS);
+            distances[*start] = 0;
+            queue.push_back(*start);
+
+            while let Some(pos) = queue.pop_front() {
+                let len = distances[pos];
+
+                let to = point_indices[pos];
+                if to != usize::MAX && to != i {
+                    let mut gates = Vec::new();
+                    let mut current = parents[pos];
+                    while current != *start {
+                        if gated(&self[current]) {
+                            gates.push(current);
+                        }
+                        current = parents[current];
+                    }
+                    gates.reverse();
+
+                    point_paths.push(PointPath { to, len, gates });
+                }
+
+                for next in valid_offsets(false, pos.0, pos.1, self.width, self.height) {
+                    if distances[next] != usize::MAX {
+                        continue;
+                    }
+
+                    let v = &self[next];
+                    if interesting(v) || passable(v) || gated(v) {
+                        distances[next] = len + 1;
+                        parents[next] = pos;
+                        queue.push_back(next);
+                    }
+                }
+            }
+
+            paths.push(point_paths);
+        }
+
+        PointGraph { points, paths }
+    }
+}
+
+/// The interesting tiles of a grid and the shortest paths between them, made by
+/// `FixedGrid::point_graph`. Points are indexed in reading order.
+#[derive(Clone, Debug)]
+pub struct PointGraph {
+    points: Vec<(usize, usize)>,
+    paths: Vec<Vec<PointPath>>,
+}
+
+/// The shortest path to another point in a `PointGraph`, with the gated tiles crossed in order.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PointPath {
+    pub to: usize,
+    pub len: usize,
+    pub gates: Vec<(usize, usize)>,
+}
+
+impl PointGraph {
+    pub fn points(&self) -> &[(usize, usize)] {
+        &self.points
+    }
+
+    pub fn index_of(&self, pos: (usize, usize)) -> Option<usize> {
+        self.points.iter().position(|p| *p == pos)
+    }
+
+    /// Get the paths to every other reachable point, shortest first.
+    pub fn paths_from(&self, index: usize) -> &[PointPath] {
+        &self.paths[index]
+    }
+
+    pub fn path(&self, from: usize, to: usize) -> Option<&PointPath> {
+        self.paths[from].iter().find(|p| p.to == to)
+    }
+
+    pub fn distance(&self, from: usize, to: usize) -> Option<usize> {
+        self.path(from, to).map(|p| p.len)
+    }
+
+    /// Convert to an undirected graph with the positions as node names. The node indices are the
+    /// same as the point indices.
+    pub fn to_graph(&self) -> Graph<(usize, usize), usize> {
+        let mut graph = Graph::new(false);
+        for pos in self.points.iter() {
+            graph.add_node(*pos);
+        }
+        for (from, paths) in self.paths.iter().enumerate() {
+            for path in paths.iter().filter(|p| p.to > from) {
+                graph.add_edge_index(from, path.to, path.len);
+            }
+        }
+
+        graph
+    }
+}
+
+impl<T> Index<(usize, usize)> for FixedGrid<T> {
+    type Output = T;
+
+    fn index(&self, idx: (usize, usize)) -> &Self::Output {
+        self.get(idx.0, idx.1).unwrap()
+    }
+}
+
+impl<T> IndexMut<(usize, usize)> for FixedGrid<T> {
+    fn index_mut(&mut self, idx: (usize, usize)) -> &mut Self::Output {
+        self.get_mut(idx.0, idx.1).unwrap()
+    }
+}
+
+impl FixedGrid<u8>
+{
+    #[allow(dead_code)]
+    pub fn print(&self) {
+        self.print_with(|v| *v as char);
+    }
+
+    pub fn from_str(s: &str) -> FixedGrid<u8> {
+        FixedGrid::parse_bytes(s.as_bytes(), |b| b)
+    }
+}
+
+impl FixedGrid<char>
+{
+    #[allow(dead_code)]
+    pub fn print(&self) {
+        self.print_with(|v| *v);
+    }
+
+    pub fn from_str(s: &str) -> FixedGrid<char> {
+        FixedGrid::parse_str(s, |c| c)
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct TinyGrid<T, const W: usize, const S: usize> {
+    data: [T; S],
+}
+
+impl<T, const W: usize, const S: usize> TinyGrid<T, W, S> {
+    pub const fn width(&self) -> usize {
+        W
+    }
+    pub const fn height(&self) -> usize {
+        S / W
+    }
+    pub fn data(&self) -> &[T] {
+        &self.data
+    }
+    pub fn lines(&self) -> impl Iterator<Item=&[T]> {
+        self.data.chunks(W)
+    }
+    pub fn line(&self, y: usize) -> &[T] {
+        &self.data[(y * W)..((y + 1) * W)]
+    }
+    pub fn iter(&self) -> impl Iterator<Item=(usize, usize, &T)> {
+        self.data.iter().enumerate().map(|(i, v)| (i % W, i / W, v))
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<&T> {
+        if x < W {
+            self.data.get(y * W + x)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut T> {
+        if x < W {
+            self.data.get_mut(y * W + x)
+        } else {
+            None
+        }
+    }
+
+    pub const fn new(data: [T; S]) -> TinyGrid<T, W, S> {
+        TinyGrid { data }
+    }
+}
+
+impl<T, const W: usize, const S: usize> TinyGrid<T, W, S> where T: Copy {
+    pub fn set(&mut self, x: usize, y: usize, v: T) {
+        if x >= W {
+            panic!("Set out of bounds")
+        }
+
+        *self.data.get_mut((y * W) + x).unwrap() = v;
+    }
+
+    /// Copy a grid of the same size, or return `None` if the size differs.
+    pub fn from_grid(grid: &FixedGrid<T>) -> Option<TinyGrid<T, W, S>> {
+        if grid.width() != W || grid.height() * W != S {
+            return None;
+        }
+
+        Some(TinyGrid { data: std::array::from_fn(|i| grid.data()[i]) })
+    }
+
+    pub fn to_grid(&self) -> FixedGrid<T> {
+        FixedGrid::from(W, S / W, self.data.to_vec())
+    }
+}
+
+impl<const W: usize, const S: usize> TinyGrid<u8, W, S> {
+    /// Parse a grid of bytes at compile time, skipping line breaks. This panics if the input
+    /// has the wrong amount of bytes.
+    pub const fn parse_bytes(input: &[u8]) -> TinyGrid<u8, W, S> {
+        Self::parse_offset(input, 0)
+    }
+
+    /// Same as `parse_bytes`, but turns the digits into their values.
+    pub const fn parse_digits(input: &[u8]) -> TinyGrid<u8, W, S> {
+        Self::parse_offset(input, b'0')
+    }
+
+    const fn parse_offset(input: &[u8], offset: u8) -> TinyGrid<u8, W, S> {
+        let mut data = [0u8; S];
+        let mut i = 0;
+        let mut n = 0;
+        while i < input.len() {
+            if input[i] != b'\n' && input[i] != b'\r' {
+                if n == S {
+                    panic!("Too much input for grid");
+                }
+
+                data[n] = input[i] - offset;
+                n += 1;
+            }
+
+            i += 1;
+        }
+        if n != S {
+            panic!("Too little input for grid");
+        }
+
+        TinyGrid { data }
+    }
+}
+
+impl<T, const W: usize, const S: usize> Display for TinyGrid<T, W, S> where T: Display {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fmt_lines(f, self.lines())
+    }
+}
+
+impl<T> Display for FixedGrid<T> where T: Display {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fmt_lines(f, self.lines())
+    }
+}
+
+fn fmt_lines<'a, T: Display + 'a>(f: &mut Formatter<'_>, lines: impl Iterator<Item=&'a [T]>) -> fmt::Result {
+    for line in lines {
+        for v in line {
+            write!(f, "{}", v)?;
+        }
+        writeln!(f)?;
+    }
+
+    Ok(())
+}
+
+/// Common API of the two-dimensional grids, so that helpers and searches can work on any of
+/// them. Unlike `FixedGrid::get`, `get` here is always bounds checked.
+pub trait Grid {
+    type Item;
+
+    fn width(&self) -> usize;
+    fn height(&self) -> usize;
+    fn get(&self, x: usize, y: usize) -> Option<&Self::Item>;
+
+    /// Get a cell that is known to be inside the grid, like the positions the searches step
+    /// to. Grids that store their cells skip the bounds check of `get` here, so outside the
+    /// grid this panics in debug builds, but may return another cell in release builds.
+    fn get_in_bounds(&self, x: usize, y: usize) -> &Self::Item {
+        self.get(x, y).unwrap()
+    }
+
+    fn in_bounds(&self, x: usize, y: usize) -> bool {
+        x < self.width() && y < self.height()
+    }
+
+    fn iter(&self) -> impl Iterator<Item=(usize, usize, &Self::Item)> {
+        let width = self.width();
+        (0..(width * self.height())).map(move |i| {
+            let (x, y) = (i % width, i / width);
+            (x, y, self.get(x, y).unwrap())
+        })
+    }
+
+    /// Iterate over the positions next to the cell that are inside the grid.
+    fn neighbours(&self, x: usize, y: usize, diagonal: bool) -> impl Iterator<Item=(usize, usize)> + use<Self> {
+        valid_offsets(diagonal, x, y, self.width(), self.height())
+    }
+
+    fn count(&self, v: Self::Item) -> usize where Self::Item: PartialEq {
+        self.iter().filter(|(_, _, v2)| **v2 == v).count()
+    }
+
+    fn find(&self, v: Self::Item) -> Option<(usize, usize)> where Self::Item: PartialEq {
+        self.iter().find(|(_, _, v2)| **v2 == v).map(|(x, y, _)| (x, y))
+    }
+
+    /// Copy the grid into a `FixedGrid`.
+    fn to_fixed_grid(&self) -> FixedGrid<Self::Item> where Self::Item: Copy {
+        FixedGrid::from(self.width(), self.height(), self.iter().map(|(_, _, v)| *v).collect())
+    }
+
+    /// Get a read-only view of the part of the grid with the top left corner at (x, y).
+    fn view(&self, x: usize, y: usize, width: usize, height: usize) -> GridView<'_, Self> where Self: Sized {
+        GridView::new(self, x, y, width, height)
+    }
+
+    /// Render the grid with one char per cell and a line break after every row.
+    fn render_with(&self, f: impl Fn(&Self::Item) -> char) -> String {
+        let mut s = String::with_capacity((self.width() + 1) * self.height());
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                s.push(f(self.get(x, y).unwrap()));
+            }
+            s.push('\n');
+        }
+
+        s
+    }
+
+    fn print_with(&self, f: impl Fn(&Self::Item) -> char) {
+        print!("{}", self.render_with(f));
+    }
+}
+
+/// Grids that `BFS` and `Dijkstra` can search. Every `Grid` is one, and hands its cells to
+/// the search as references. `TiledGrid` computes its cells, so it hands out values instead.
+pub trait SearchGrid {
+    type Cell<'a>: Copy where Self: 'a;
+
+    /// Get the width and height.
+    fn size(&self) -> (usize, usize);
+
+    /// Get a cell that is known to be inside the grid, with the same rules as
+    /// `Grid::get_in_bounds`.
+    fn cell(&self, x: usize, y: usize) -> Self::Cell<'_>;
+}
+
+impl<G> SearchGrid for G where G: Grid {
+    type Cell<'a> = &'a G::Item where G: 'a;
+
+    fn size(&self) -> (usize, usize) {
+        (self.width(), self.height())
+    }
+    fn cell(&self, x: usize, y: usize) -> &G::Item {
+        self.get_in_bounds(x, y)
+    }
+}
+
+/// A base grid repeated a number of times in each direction, where the values of each tile go
+/// through a transform that gets the tile's position. Cells are computed every time they are
+/// read and never stored, so searching it takes no more memory than searching the base grid
+/// would, apart from the search's own state.
+///
+/// It is not a `Grid`, since there are no stored cells to reference. Use `to_fixed_grid` to get
+/// the `Grid` helpers on a tiling that is small enough to store.
+#[derive(Clone)]
+pub struct TiledGrid<'a, G, F> {
+    base: &'a G,
+    tiles: (usize, usize),
+    transform: F,
+}
+
+impl<'a, G, F> TiledGrid<'a, G, F>
+    where
+        G: Grid,
+        G::Item: Copy,
+        F: Fn(G::Item, isize, isize) -> G::Item,
+{
+    pub fn base(&self) -> &'a G {
+        self.base
+    }
+
+    /// Get the amount of tiles in each direction.
+    pub fn tiles(&self) -> (usize, usize) {
+        self.tiles
+    }
+
+    pub fn value(&self, x: usize, y: usize) -> Option<G::Item> {
+        let (width, height) = self.size();
+        if x < width && y < height {
+            Some(self.cell(x, y))
+        } else {
+            None
+        }
+    }
+
+    /// Compute every cell into a `FixedGrid`.
+    pub fn to_fixed_grid(&self) -> FixedGrid<G::Item> {
+        let (width, height) = self.size();
+        let data = (0..height).flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| self.cell(x, y))
+            .collect();
+
+        FixedGrid::from(width, height, data)
+    }
+
+    pub fn new(base: &'a G, tiles_x: usize, tiles_y: usize, transform: F) -> TiledGrid<'a, G, F> {
+        TiledGrid { base, tiles: (tiles_x, tiles_y), transform }
+    }
+}
+
+impl<'a, G, F> SearchGrid for TiledGrid<'a, G, F>
+    where
+        G: Grid,
+        G::Item: Copy,
+        F: Fn(G::Item, isize, isize) -> G::Item,
+{
+    type Cell<'b> = G::Item where Self: 'b;
+
+    fn size(&self) -> (usize, usize) {
+        (self.base.width() * self.tiles.0, self.base.height() * self.tiles.1)
+    }
+    fn cell(&self, x: usize, y: usize) -> G::Item {
+        let (w, h) = (self.base.width(), self.base.height());
+        debug_assert!(x < w * self.tiles.0 && y < h * self.tiles.1, "({}, {}) is outside the tiled grid", x, y);
+
+        (self.transform)(*self.base.get_in_bounds(x % w, y % h), (x / w) as isize, (y / h) as isize)
+    }
+}
+
+/// A base grid repeated forever in every direction, with the same transform as `TiledGrid`.
+/// The base grid is the tile at (0, 0). Values are computed every time they are read. Since
+/// it has no size, it is not a `SearchGrid`, but it can be explored with a `Search`.
+#[derive(Clone)]
+pub struct InfiniteTiledGrid<'a, G, F> {
+    base: &'a G,
+    transform: F,
+}
+
+impl<'a, G, F> InfiniteTiledGrid<'a, G, F>
+    where
+        G: Grid,
+        G::Item: Copy,
+        F: Fn(G::Item, isize, isize) -> G::Item,
+{
+    pub fn base(&self) -> &'a G {
+        self.base
+    }
+
+    /// Get the value at the position, or `None` if the base grid is empty.
+    pub fn value(&self, x: isize, y: isize) -> Option<G::Item> {
+        let (w, h) = (self.base.width() as isize, self.base.height() as isize);
+        if w == 0 || h == 0 {
+            return None;
+        }
+
+        let v = *self.base.get(x.rem_euclid(w) as usize, y.rem_euclid(h) as usize)?;
+        Some((self.transform)(v, x.div_euclid(w), y.div_euclid(h)))
+    }
+
+    pub fn new(base: &'a G, transform: F) -> InfiniteTiledGrid<'a, G, F> {
+        InfiniteTiledGrid { base, transform }
+    }
+}
+
+/// Grids whose cells can be changed in place.
+pub trait GridMut: Grid {
+    fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut Self::Item>;
+}
+
+/// Read-only window into a part of another grid, made with `Grid::view`.
+#[derive(Clone, Copy)]
+pub struct GridView<'a, G> {
+    grid: &'a G,
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+}
+
+impl<'a, G> GridView<'a, G> where G: Grid {
+    /// Get the position of the view's (0, 0) in the underlying grid.
+    pub fn offset(&self) -> (usize, usize) {
+        (self.x, self.y)
+    }
+
+    pub fn new(grid: &'a G, x: usize, y: usize, width: usize, height: usize) -> GridView<'a, G> {
+        assert!(x + width <= grid.width() && y + height <= grid.height(), "View falls outside grid");
+
+        GridView { grid, x, y, width, height }
+    }
+}
+
+impl<'a, G> Grid for GridView<'a, G> where G: Grid {
+    type Item = G::Item;
+
+    fn width(&self) -> usize {
+        self.width
+    }
+    fn height(&self) -> usize {
+        self.height
+    }
+    fn get(&self, x: usize, y: usize) -> Option<&G::Item> {
+        if x < self.width && y < self.height {
+            self.grid.get(self.x + x, self.y + y)
+        } else {
+            None
+        }
+    }
+    fn get_in_bounds(&self, x: usize, y: usize) -> &G::Item {
+        debug_assert!(x < self.width && y < self.height, "({}, {}) is outside the {}x{} view", x, y, self.width, self.height);
+        self.grid.get_in_bounds(self.x + x, self.y + y)
+    }
+}
+
+impl<T> Grid for FixedGrid<T> {
+    type Item = T;
+
+    fn width(&self) -> usize {
+        self.width
+    }
+    fn height(&self) -> usize {
+        self.height
+    }
+    fn get(&self, x: usize, y: usize) -> Option<&T> {
+        self.get_safe(x, y)
+    }
+    fn get_in_bounds(&self, x: usize, y: usize) -> &T {
+        debug_assert!(x < self.width && y < self.height, "({}, {}) is outside the {}x{} grid", x, y, self.width, self.height);
+        &self[(x, y)]
+    }
+}
+
+impl<T> GridMut for FixedGrid<T> {
+    fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut T> {
+        if x < self.width && y < self.height {
+            self.data.get_mut(y * self.width + x)
+        } else {
+            None
+        }
+    }
+}
+
+impl<T, const W: usize, const S: usize> Grid for TinyGrid<T, W, S> {
+    type Item = T;
+
+    fn width(&self) -> usize {
+        W
+    }
+    fn height(&self) -> usize {
+        S / W
+    }
+    fn get(&self, x: usize, y: usize) -> Option<&T> {
+        TinyGrid::get(self, x, y)
+    }
+    fn get_in_bounds(&self, x: usize, y: usize) -> &T {
+        debug_assert!(x < W, "({}, {}) is outside the {}x{} grid", x, y, W, S / W);
+        &self.data[y * W + x]
+    }
+    fn iter(&self) -> impl Iterator<Item=(usize, usize, &T)> {
+        TinyGrid::iter(self)
+    }
+}
+
+impl<T, const W: usize, const S: usize> GridMut for TinyGrid<T, W, S> {
+    fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut T> {
+        TinyGrid::get_mut(self, x, y)
+    }
+}
+
+impl Grid for BitGrid {
+    type Item = bool;
+
+    fn width(&self) -> usize {
+        self.width
+    }
+    fn height(&self) -> usize {
+        self.height
+    }
+    fn get(&self, x: usize, y: usize) -> Option<&bool> {
+        BitGrid::get(self, x, y).map(|v| if v { &true } else { &false })
+    }
+}
+
+impl<T> Grid for GridSet<T> where T: Copy {
+    type Item = T;
+
+    fn width(&self) -> usize {
+        match self.chunk_bounds {
+            Some(((x1, _), (x2, _))) => ((x2 - x1 + 1) * self.chunk_width) as usize,
+            None => 0,
+        }
+    }
+    fn height(&self) -> usize {
+        match self.chunk_bounds {
+            Some(((_, y1), (_, y2))) => ((y2 - y1 + 1) * self.chunk_height) as usize,
+            None => 0,
+        }
+    }
+    fn get(&self, x: usize, y: usize) -> Option<&T> {
+        if !self.in_bounds(x, y) {
+            return None;
+        }
+
+        let (ox, oy) = self.origin();
+        Some(self.get_signed(ox + x as isize, oy + y as isize))
+    }
+}
+
+impl<T> GridMut for GridSet<T> where T: Copy {
+    fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut T> {
+        if !self.in_bounds(x, y) {
+            return None;
+        }
+
+        let (ox, oy) = self.origin();
+        Some(self.get_signed_mut(ox + x as isize, oy + y as isize))
+    }
+}
+
+/// Grid of booleans packed into 64-bit words. Every row starts on a new word, so rows can be
+/// combined and shifted a word at a time. Bits past the width are always kept off.
+#[derive(Clone, Eq, PartialEq, Hash)]
+pub struct BitGrid {
+    words: Vec<u64>,
+    stride: usize,
+    width: usize,
+    height: usize,
+}
+
+impl BitGrid {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    pub fn height(&self) -> usize {
+        self.height
+    }
+    pub fn get(&self, x: usize, y: usize) -> Option<bool> {
+        if x >= self.width || y >= self.height {
+            None
+        } else {
+            Some(self.words[y * self.stride + (x / 64)] & (1 << (x % 64)) != 0)
+        }
+    }
+    /// Same as `get`, but out of bounds is off.
+    #[inline]
+    pub fn has(&self, x: usize, y: usize) -> bool {
+        self.get(x, y).unwrap_or(false)
+    }
+    pub fn set(&mut self, x: usize, y: usize, v: bool) {
+        if x >= self.width || y >= self.height {
+            panic!("Set out of bounds ({}, {}) in {}x{} grid", x, y, self.width, self.height)
+        }
+
+        let word = &mut self.words[y * self.stride + (x / 64)];
+        if v {
+            *word |= 1 << (x % 64);
+        } else {
+            *word &= !(1 << (x % 64));
+        }
+    }
+    /// Set the bit and return whether it was off before, which makes visited checks a single call.
+    pub fn insert(&mut self, x: usize, y: usize) -> bool {
+        let was_set = self.has(x, y);
+        self.set(x, y, true);
+        !was_set
+    }
+    pub fn clear(&mut self) {
+        self.words.fill(0);
+    }
+    pub fn fill(&mut self, v: bool) {
+        self.words.fill(if v { !0 } else { 0 });
+        self.mask_padding();
+    }
+
+    /// The words of each row, lowest x in the lowest bit.
+    pub fn lines(&self) -> impl Iterator<Item=&[u64]> {
+        self.words.chunks(self.stride.max(1)).take(self.height)
+    }
+    pub fn line(&self, y: usize) -> &[u64] {
+        &self.words[(y * self.stride)..((y + 1) * self.stride)]
+    }
+    pub fn iter(&self) -> impl Iterator<Item=(usize, usize, bool)> + '_ {
+        (0..self.height).flat_map(move |y| (0..self.width).map(move |x| (x, y, self.has(x, y))))
+    }
+    /// Iterate over the positions of the bits that are on, skipping empty words.
+    pub fn iter_ones(&self) -> impl Iterator<Item=(usize, usize)> + '_ {
+        self.words.iter().enumerate().flat_map(move |(i, word)| {
+            let y = i / self.stride;
+            let x_base = (i % self.stride) * 64;
+            let mut word = *word;
+
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+
+                let bit = word.trailing_zeros() as usize;
+                word &= word - 1;
+                Some((x_base + bit, y))
+            })
+        })
+    }
+
+    /// Count the bits with the value, using popcount over whole words.
+    pub fn count(&self, v: bool) -> usize {
+        let ones = self.count_ones();
+        if v { ones } else { (self.width * self.height) - ones }
+    }
+    pub fn count_ones(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    pub fn and(&mut self, other: &BitGrid) {
+        self.combine(other, |a, b| a & b);
+    }
+    pub fn or(&mut self, other: &BitGrid) {
+        self.combine(other, |a, b| a | b);
+    }
+    pub fn xor(&mut self, other: &BitGrid) {
+        self.combine(other, |a, b| a ^ b);
+    }
+    pub fn invert(&mut self) {
+        for word in self.words.iter_mut() {
+            *word = !*word;
+        }
+        self.mask_padding();
+    }
+
+    /// Move every bit by the offset, dropping the bits that end up outside the grid and
+    /// turning off the ones that are uncovered.
+    pub fn shift(&mut self, dx: isize, dy: isize) {
+        if self.words.is_empty() {
+            return;
+        }
+
+        if dx != 0 {
+            let words = dx.unsigned_abs() / 64;
+            let bits = (dx.unsigned_abs() % 64) as u32;
+
+            for row in self.words.chunks_mut(self.stride) {
+                if dx > 0 {
+                    for i in (0..row.len()).rev() {
+                        let low = if i >= words { row[i - words] } else { 0 };
+                        let lower = if i > words { row[i - words - 1] } else { 0 };
+                        row[i] = if bits == 0 { low } else { (low << bits) | (lower >> (64 - bits)) };
+                    }
+                } else {
+                    for i in 0..row.len() {
+                        let high = row.get(i + words).copied().unwrap_or(0);
+                        let higher = row.get(i + words + 1).copied().unwrap_or(0);
+                        row[i] = if bits == 0 { high } else { (high >> bits) | (higher << (64 - bits)) };
+                    }
+                }
+            }
+
+            self.mask_padding();
+        }
+
+        if dy != 0 {
+            let rows = dy.unsigned_abs().min(self.height);
+            let offset = rows * self.stride;
+            let len = self.words.len();
+
+            if dy > 0 {
+                self.words.copy_within(0..(len - offset), offset);
+                self.words[..offset].fill(0);
+            } else {
+                self.words.copy_within(offset.., 0);
+                self.words[(len - offset)..].fill(0);
+            }
+        }
+    }
+
+    fn combine(&mut self, other: &BitGrid, op: impl Fn(u64, u64) -> u64) {
+        assert_eq!(self.width, other.width);
+        assert_eq!(self.height, other.height);
+
+        for (a, b) in self.words.iter_mut().zip(other.words.iter()) {
+            *a = op(*a, *b);
+        }
+    }
+
+    fn mask_padding(&mut self) {
+        if self.width.is_multiple_of(64) {
+            return;
+        }
+
+        let mask = (1u64 << (self.width % 64)) - 1;
+        for row in self.words.chunks_mut(self.stride) {
+            *row.last_mut().unwrap() &= mask;
+        }
+    }
+
+    /// Build from the cells of another grid where the predicate holds.
+    pub fn from_grid<T>(grid: &FixedGrid<T>, predicate: impl Fn(&T) -> bool) -> BitGrid {
+        let mut bits = BitGrid::new(grid.width, grid.height);
+        for (x, y, v) in grid.iter() {
+            if predicate(v) {
+                bits.set(x, y, true);
+            }
+        }
+
+        bits
+    }
+
+    pub fn to_grid<T: Copy>(&self, off: T, on: T) -> FixedGrid<T> {
+        FixedGrid::from(self.width, self.height, self.iter().map(|(_, _, v)| if v { on } else { off }).collect())
+    }
+
+    #[allow(dead_code)]
+    pub fn print(&self) {
+        self.print_with(|v| if *v { '#' } else { '.' });
+    }
+
+    pub fn empty() -> BitGrid {
+        BitGrid::new(0, 0)
+    }
+
+    pub fn new(width: usize, height: usize) -> BitGrid {
+        let stride = width.div_ceil(64);
+
+        BitGrid {
+            words: vec![0; stride * height],
+            stride,
+            width,
+            height,
+        }
+    }
+}
+
+impl Index<(usize, usize)> for BitGrid {
+    type Output = bool;
+
+    fn index(&self, idx: (usize, usize)) -> &Self::Output {
+        if self.get(idx.0, idx.1).unwrap() { &true } else { &false }
+    }
+}
+
+const OFFSETS_CARDINAL: [(usize, usize); 4] = [
+    (0, !0),
+    (!0, 0),
+    (1, 0),
+    (0, 1),
+];
+
+const OFFSETS_DIAGONAL: [(usize, usize); 8] = [
+    (!0, !0),
+    (!0, 0),
+    (!0, 1),
+    (0, !0),
+    (0, 1),
+    (1, !0),
+    (1, 0),
+    (1, 1),
+];
+
+#[derive(Debug)]
+pub enum BFSStep<S> {
+    Continue(S),
+    Found(S),
+    Warp(usize, usize, S),
+    WarpLevel(usize, usize, isize, S),
+    DeadEnd,
+}
+
+/// A one-way connection between two tiles, taken after searching from the `from` tile. The
+/// level change only matters for multilevel searches.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Portal {
+    pub from: (usize, usize),
+    pub to: (usize, usize),
+    pub cost: i64,
+    pub level_change: isize,
+}
+
+impl Portal {
+    pub fn new(from: (usize, usize), to: (usize, usize), cost: i64) -> Portal {
+        Portal { from, to, cost, level_change: 0 }
+    }
+
+    pub fn with_level_change(mut self, level_change: isize) -> Portal {
+        self.level_change = level_change;
+        self
+    }
+}
+
+#[derive(Clone, Default)]
+struct PortalTable {
+    portals: Vec<Portal>,
+    by_pos: FxHashMap<(usize, usize), SmallVec<[Portal; 2]>>,
+}
+
+impl PortalTable {
+    fn is_empty(&self) -> bool {
+        self.portals.is_empty()
+    }
+
+    fn from(&self, pos: (usize, usize)) -> SmallVec<[Portal; 2]> {
+        self.by_pos.get(&pos).cloned().unwrap_or_default()
+    }
+
+    fn add(&mut self, portal: Portal) {
+        self.portals.push(portal);
+        self.by_pos.entry(portal.from).or_default().push(portal);
+    }
+
+    /// Panic if a portal leads outside the grid, since the searches read the cells they step
+    /// to without a bounds check.
+    fn check_targets(&self, width: usize, height: usize) {
+        for portal in self.portals.iter() {
+            check_target("Portal", portal.from, portal.to, width, height);
+        }
+    }
+}
+
+fn check_target(kind: &str, from: (usize, usize), to: (usize, usize), width: usize, height: usize) {
+    assert!(to.0 < width && to.1 < height, "{} from {:?} leads to {:?}, outside the {}x{} grid", kind, from, to, width, height);
+}
+
+const DEFAULT_LEVEL_BOUNDS: (usize, usize) = (0, 10000);
+
+fn change_level(level: usize, level_change: isize, (min, max): (usize, usize)) -> Option<usize> {
+    let new_level = level as isize + level_change;
+    if new_level < min as isize || new_level > max as isize {
+        None
+    } else {
+        Some(new_level as usize)
+    }
+}
+
+const NO_PARENT: (usize, usize, usize) = (!0, !0, !0);
+
+/// Queue entry with the position, length, level, parent and state.
+type BFSEntry<S> = (usize, usize, usize, usize, (usize, usize, usize), S);
+
+/// BFS struct is to keep some state between runs to avoid needless allocations. The visited
+/// sets are bit grids, so each extra level of a multilevel search costs one bit per tile.
+#[derive(Clone)]
+pub struct BFS<S> {
+    visited: Vec<BitGrid>,
+    parents: Option<Vec<FixedGrid<(usize, usize, usize)>>>,
+    queue: VecDeque<BFSEntry<S>>,
+    found_pos: Option<(usize, usize)>,
+    found_level: usize,
+    portals: PortalTable,
+    level_bounds: (usize, usize),
+}
+
+impl<S> BFS<S> where S: Clone + Default {
+    /// Get the position where it was found in the last run.
+    pub fn found_pos(&self) -> Option<(usize, usize)> {
+        self.found_pos
+    }
+
+    /// Get the level where it was found in the last run.
+    pub fn found_level(&self) -> Option<usize> {
+        self.found_pos.map(|_| self.found_level)
+    }
+
+    /// Get the route from the start to where it was found in the last run, including both ends.
+    /// This is only available when created `with_path`.
+    pub fn path(&self) -> Option<Vec<(usize, usize)>> {
+        let parents = self.parents.as_ref()?;
+        let (mut x, mut y) = self.found_pos?;
+        let mut level = self.found_level;
+        let mut path = vec![(x, y)];
+
+        loop {
+            let (px, py, plevel) = parents[level][(x, y)];
+            if (px, py, plevel) == NO_PARENT {
+                break;
+            }
+
+            path.push((px, py));
+            x = px;
+            y = py;
+            level = plevel;
+        }
+
+        path.reverse();
+        Some(path)
+    }
+
+    /// Run BFS on this grid. This will reset all state, so is safe to call multiple times.
+    /// Level changes from warps and portals that fall outside the level bounds are skipped.
+    pub fn run_multilevel<'a, G: SearchGrid>(&'a mut self, grid: &'a G, start_x: usize, start_y: usize, start_level: usize, diagonal: bool, check: impl Fn(G::Cell<'a>, (usize, usize), usize, &S) -> BFSStep<S>) -> Option<(G::Cell<'a>, usize, S)> {
+        for i in 0..self.visited.len() {
+            self.visited[i].clear();
+        }
+        if let Some(parents) = self.parents.as_mut() {
+            for level_parents in parents.iter_mut() {
+                level_parents.data.fill(NO_PARENT);
+            }
+        }
+        let (width, height) = grid.size();
+        assert!(start_x < width && start_y < height, "Start ({}, {}) is outside the {}x{} grid", start_x, start_y, width, height);
+        self.portals.check_targets(width, height);
+        self.prepare_level(start_level, width, height);
+
+        self.found_pos = None;
+
+        self.queue.clear();
+        self.queue.push_back((start_x, start_y, 0, start_level, NO_PARENT, S::default()));
+
+        let offsets: &[(usize, usize)] = if diagonal { &OFFSETS_DIAGONAL } else { &OFFSETS_CARDINAL };
+
+        while let Some((x, y, l, level, parent, state)) = self.queue.pop_front() {
+            if let Some(parents) = self.parents.as_mut() {
+                if !self.visited[level][(x, y)] {
+                    parents[level][(x, y)] = parent;
+                }
+            }
+
+            self.visited[level].set(x, y, true);
+            let v = grid.cell(x, y);
+
+            match check(v, (x, y), level, &state) {
+                BFSStep::Continue(new_state) => {
+                    for (x_offset, y_offset) in offsets.iter() {
+                        let x2 = x.wrapping_add(*x_offset);
+                        if x2 >= width {
+                            continue;
+                        }
+                        let y2 = y.wrapping_add(*y_offset);
+                        if y2 >= height {
+                            continue;
+                        }
+                        if self.visited[level][(x2, y2)] {
+                            continue;
+                        }
+
+                        self.queue.push_back((x2, y2, l + 1, level, (x, y, level), new_state.clone()));
+                    }
+
+                    if !self.portals.is_empty() {
+                        for portal in self.portals.from((x, y)) {
+                            let new_level = match change_level(level, portal.level_change, self.level_bounds) {
+                                Some(new_level) => new_level,
+                                None => continue,
+                            };
+                            self.prepare_level(new_level, width, height);
+                            if self.visited[new_level][portal.to] {
+                                continue;
+                            }
+
+                            let (x2, y2) = portal.to;
+                            if portal.cost == 0 {
+                                self.queue.push_front((x2, y2, l, new_level, (x, y, level), new_state.clone()));
+                            } else {
+                                self.queue.push_back((x2, y2, l + 1, new_level, (x, y, level), new_state.clone()));
+                            }
+                        }
+                    }
+                }
+                BFSStep::Found(new_state) => {
+                    self.found_pos = Some((x, y));
+                    self.found_level = level;
+                    return Some((v, l, new_state));
+                }
+                BFSStep::Warp(x2, y2, new_state) => {
+                    check_target("Warp", (x, y), (x2, y2), width, height);
+                    self.queue.push_front((x2, y2, l, level, (x, y, level), new_state.clone()));
+                }
+                BFSStep::WarpLevel(x2, y2, level_change, new_state) => {
+                    check_target("Warp", (x, y), (x2, y2), width, height);
+                    if let Some(new_level) = change_level(level, level_change, self.level_bounds) {
+                        self.prepare_level(new_level, width, height);
+
+                        self.queue.push_front((x2, y2, l, new_level, (x, y, level), new_state.clone()));
+                    }
+                }
+                BFSStep::DeadEnd => {
+                    // Do nothing.
+                }
+            }
+        }
+
+        None
+    }
+
+    pub fn run<'a, G: SearchGrid>(&'a mut self, grid: &'a G, start_x: usize, start_y: usize, diagonal: bool, check: impl Fn(G::Cell<'a>, (usize, usize), &S) -> BFSStep<S>) -> Option<(G::Cell<'a>, usize, S)> {
+        self.run_multilevel(grid, start_x, start_y, 0, diagonal, |v, pos, _, state| {
+            check(v, pos, state)
+        })
+    }
+
+    fn prepare_level(&mut self, level: usize, width: usize, height: usize) {
+        while self.visited.len() <= level {
+            self.visited.push(BitGrid::empty());
+        }
+        if self.visited[level].width < width || self.visited[level].height < height {
+            self.visited[level] = BitGrid::new(width, height);
+        }
+
+        if let Some(parents) = self.parents.as_mut() {
+            while parents.len() <= level {
+                parents.push(FixedGrid::empty());
+            }
+            if parents[level].width < width || parents[level].height < height {
+                parents[level] = FixedGrid::new(width, height, NO_PARENT);
+            }
+        }
+    }
+
+    /// Record the route taken to every visited tile, so that `path` can be used after a run.
+    pub fn with_path(mut self) -> BFS<S> {
+        self.parents = Some(Vec::new());
+        self
+    }
+
+    /// Add portals to take after searching from their `from` tile. Since this is a BFS, the
+    /// cost must be 0 or 1 steps.
+    pub fn with_portals(mut self, portals: impl IntoIterator<Item=Portal>) -> BFS<S> {
+        for portal in portals {
+            assert!(portal.cost == 0 || portal.cost == 1, "BFS portal cost must be 0 or 1, got {}", portal.cost);
+            self.portals.add(portal);
+        }
+
+        self
+    }
+
+    /// Set the lowest and highest level (inclusive) that can be reached in multilevel runs.
+    /// The default is 0 to 10000.
+    pub fn with_level_bounds(mut self, min: usize, max: usize) -> BFS<S> {
+        self.level_bounds = (min, max);
+        self
+    }
+
+    pub fn new() -> BFS<S> {
+        BFS {
+            visited: vec![BitGrid::empty(); 1],
+            parents: None,
+            queue: VecDeque::with_capacity(64),
+            found_pos: None,
+            found_level: 0,
+            portals: PortalTable::default(),
+            level_bounds: DEFAULT_LEVEL_BOUNDS,
+        }
+    }
+}
+
+/// How `distance_field` measures the distance to a source.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DistanceMetric {
+    /// Steps along the axes, ignoring walls.
+    Manhattan,
+    /// Steps where diagonals count as one, ignoring walls.
+    Chebyshev,
+    /// Length of the shortest path through passable cells.
+    Path { diagonal: bool },
+}
 
-    pub fn new() -> BFS<S> {
-        BFS {
-            visited: vec![FixedGrid::empty(); 1],
-            queue: VecDeque::with_capacity(64),
-            found_pos: None,
+/// Which source a cell in a `DistanceField` is closest to.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Claim {
+    /// The cell is not passable, or no source can reach it.
+    Unreached,
+    /// Index of the closest source.
+    Source(usize),
+    /// Several sources are equally close.
+    Tie,
+}
+
+/// Distance from every cell to the closest of a set of sources, made by `distance_field`.
+#[derive(Clone)]
+pub struct DistanceField {
+    distances: FixedGrid<u32>,
+    claims: FixedGrid<Claim>,
+}
+
+impl DistanceField {
+    /// Get the distances, where unreached cells are `u32::MAX`.
+    pub fn distances(&self) -> &FixedGrid<u32> {
+        &self.distances
+    }
+
+    pub fn claims(&self) -> &FixedGrid<Claim> {
+        &self.claims
+    }
+
+    pub fn distance(&self, x: usize, y: usize) -> Option<u32> {
+        self.distances.get_safe(x, y).copied().filter(|d| *d != u32::MAX)
+    }
+
+    pub fn claim(&self, x: usize, y: usize) -> Claim {
+        self.claims.get_safe(x, y).copied().unwrap_or(Claim::Unreached)
+    }
+
+    /// Count the cells claimed by the source alone.
+    pub fn area(&self, source: usize) -> usize {
+        self.claims.count(Claim::Source(source))
+    }
+
+    /// Check whether the source claims a cell on the edge of the grid, which means its area
+    /// would keep growing if the grid was larger.
+    pub fn touches_edge(&self, source: usize) -> bool {
+        let (w, h) = (self.claims.width(), self.claims.height());
+        if w == 0 || h == 0 {
+            return false;
+        }
+
+        let claim = Claim::Source(source);
+
+        (0..w).any(|x| self.claims[(x, 0)] == claim || self.claims[(x, h - 1)] == claim)
+            || (0..h).any(|y| self.claims[(0, y)] == claim || self.claims[(w - 1, y)] == claim)
+    }
+}
+
+/// Find the distance from every passable cell to the closest source, and which source that
+/// is. This is a BFS from all sources at once, so a cell is a tie if the cells it is reached
+/// from are claimed by different sources or are ties themselves. The geometric metrics walk
+/// over every cell, but leave impassable cells unreached in the result.
+pub fn distance_field<G: Grid>(grid: &G, sources: &[(usize, usize)], passable: impl Fn(&G::Item) -> bool, metric: DistanceMetric) -> DistanceField {
+    let (width, height) = (grid.width(), grid.height());
+    let mut distances = FixedGrid::new(width, height, u32::MAX);
+    let mut claims = FixedGrid::new(width, height, Claim::Unreached);
+    let mut queue = VecDeque::with_capacity(sources.len() * 4);
+
+    let (diagonal, walls) = match metric {
+        DistanceMetric::Manhattan => (false, false),
+        DistanceMetric::Chebyshev => (true, false),
+        DistanceMetric::Path { diagonal } => (diagonal, true),
+    };
+
+    for (i, pos) in sources.iter().enumerate() {
+        if distances[*pos] == 0 {
+            claims[*pos] = Claim::Tie;
+        } else {
+            distances[*pos] = 0;
+            claims[*pos] = Claim::Source(i);
+            queue.push_back(*pos);
+        }
+    }
+
+    while let Some((x, y)) = queue.pop_front() {
+        let distance = distances[(x, y)] + 1;
+        let claim = claims[(x, y)];
+
+        for pos in valid_offsets(diagonal, x, y, width, height) {
+            if walls && !passable(grid.get_in_bounds(pos.0, pos.1)) {
+                continue;
+            }
+
+            if distances[pos] == u32::MAX {
+                distances[pos] = distance;
+                claims[pos] = claim;
+                queue.push_back(pos);
+            } else if distances[pos] == distance && claims[pos] != claim {
+                claims[pos] = Claim::Tie;
+            }
+        }
+    }
+
+    if !walls {
+        for (x, y, v) in grid.iter() {
+            if !passable(v) {
+                distances[(x, y)] = u32::MAX;
+                claims[(x, y)] = Claim::Unreached;
+            }
         }
     }
+
+    DistanceField { distances, claims }
 }
 
+const NO_PARENT_POS: (usize, usize) = (!0, !0);
+
+/// Dijkstra and A* search on a grid.
+///
+/// The radix heap is used by default, which requires that the cost plus heuristic of a step
+/// never goes below that of the last expanded position. Debug builds check this and panic
+/// with the position that broke it. Use `with_binary_heap` for negative costs or inconsistent
+/// heuristics.
 #[derive(Clone)]
 pub struct Dijkstra {
-    visited: FixedGrid<i64>,
+    visited: Vec<FixedGrid<i64>>,
+    parents: Option<Vec<FixedGrid<(usize, usize, usize)>>>,
     searches: RadixHeapMap<DijkstraSearch, ()>,
+    binary_heap: BinaryHeap<DijkstraSearch>,
+    use_binary_heap: bool,
+    last_popped: i64,
+    stats: HeapStats,
     found_pos: Option<(usize, usize)>,
     found_cost: Option<i64>,
+    found_level: usize,
+    found_all: Vec<((usize, usize), usize, i64)>,
     diagonal: bool,
-    start_pos: (usize, usize),
-    start_cost: i64,
+    starts: Vec<((usize, usize), i64)>,
+    start_level: usize,
+    portals: PortalTable,
+    level_bounds: (usize, usize),
 }
 
 impl Dijkstra {
@@ -529,68 +2136,340 @@ impl Dijkstra {
         self.found_cost
     }
 
+    /// Get the level where it was found in the last run.
+    pub fn found_level(&self) -> Option<usize> {
+        self.found_pos.map(|_| self.found_level)
+    }
+
+    /// Get every target found by the last `run_all` as position, level and cost, cheapest first.
+    pub fn found_all(&self) -> &[((usize, usize), usize, i64)] {
+        &self.found_all
+    }
+
+    /// Get the heap statistics of the last run.
+    pub fn heap_stats(&self) -> HeapStats {
+        self.stats
+    }
+
+    /// Get the costs on the start level of the last run.
     pub fn visited(&self) -> &FixedGrid<i64> {
-        &self.visited
+        &self.visited[self.start_level]
+    }
+
+    /// Get the costs on another level of the last run, if it was reached.
+    pub fn visited_level(&self, level: usize) -> Option<&FixedGrid<i64>> {
+        self.visited.get(level).filter(|v| v.width > 0)
+    }
+
+    /// Get the route to where it was found in the last run, including the start and end.
+    /// This is only available when created `with_path`.
+    pub fn path(&self) -> Option<Vec<(usize, usize)>> {
+        self.path_from(self.found_pos?, self.found_level)
+    }
+
+    /// Get the cheapest route found to any reached position on the start level in the last run,
+    /// starting from whichever start position it came from.
+    pub fn path_to(&self, pos: (usize, usize)) -> Option<Vec<(usize, usize)>> {
+        self.path_from(pos, self.start_level)
+    }
+
+    fn path_from(&self, pos: (usize, usize), level: usize) -> Option<Vec<(usize, usize)>> {
+        let parents = self.parents.as_ref()?;
+        let reached = *self.visited_level(level)?.get_safe(pos.0, pos.1)? != i64::MAX;
+        if !reached && parents[level][pos] == NO_PARENT {
+            return None;
+        }
+
+        let mut path = vec![pos];
+        let (mut x, mut y, mut level) = (pos.0, pos.1, level);
+        while parents[level][(x, y)] != NO_PARENT {
+            (x, y, level) = parents[level][(x, y)];
+            path.push((x, y));
+        }
+
+        path.reverse();
+        Some(path)
+    }
+
+    /// Search until the first `DijkstraStep::Found`.
+    pub fn run<'a, G: SearchGrid>(&mut self, grid: &'a G, check: impl Fn(G::Cell<'a>, (usize, usize)) -> DijkstraStep) {
+        self.search(grid, 0, true, |v, pos, _| check(v, pos));
+    }
+
+    /// Search until the first `DijkstraStep::Found`, starting on the given level. Portals can
+    /// move the search between levels within the level bounds.
+    pub fn run_multilevel<'a, G: SearchGrid>(&mut self, grid: &'a G, start_level: usize, check: impl Fn(G::Cell<'a>, (usize, usize), usize) -> DijkstraStep) {
+        self.search(grid, start_level, true, check);
+    }
+
+    /// Search the whole reachable grid, collecting every position where `check` returns
+    /// `DijkstraStep::Found`. The search continues through found positions as if they were
+    /// `DijkstraStep::Continue` with no heuristic. A position reached through portals is found
+    /// once per level. The cheapest one is also available from `found_pos`, `found_level` and
+    /// `found_cost`.
+    pub fn run_all<'a, G: SearchGrid>(&mut self, grid: &'a G, check: impl Fn(G::Cell<'a>, (usize, usize)) -> DijkstraStep) -> &[((usize, usize), usize, i64)] {
+        self.search(grid, 0, false, |v, pos, _| check(v, pos));
+
+        self.found_all.sort_by_key(|(pos, level, cost)| (*pos, *level, *cost));
+        self.found_all.dedup_by_key(|(pos, level, _)| (*pos, *level));
+        self.found_all.sort_by_key(|(pos, level, cost)| (*cost, *pos, *level));
+        if let Some((pos, level, cost)) = self.found_all.first() {
+            self.found_pos = Some(*pos);
+            self.found_level = *level;
+            self.found_cost = Some(*cost);
+        }
+
+        &self.found_all
+    }
+
+    /// Get the cost from the nearest start to every reachable position. Unreachable positions
+    /// are left at `i64::MAX`.
+    pub fn distance_map<'a, G: SearchGrid>(&mut self, grid: &'a G, check: impl Fn(G::Cell<'a>, (usize, usize)) -> DijkstraStep) -> &FixedGrid<i64> {
+        self.run_all(grid, check);
+
+        self.visited()
     }
 
-    pub fn run<'a, T>(&mut self, grid: &'a FixedGrid<T>, check: impl Fn(&'a T, (usize, usize)) -> DijkstraStep) {
-        self.visited = FixedGrid::new(grid.width, grid.height, i64::MAX);
+    fn search<'a, G: SearchGrid>(&mut self, grid: &'a G, start_level: usize, stop_at_first: bool, check: impl Fn(G::Cell<'a>, (usize, usize), usize) -> DijkstraStep) {
+        let (width, height) = grid.size();
+        self.portals.check_targets(width, height);
+        reset_levels(&mut self.visited, width, height, i64::MAX);
+        if let Some(parents) = self.parents.as_mut() {
+            reset_levels(parents, width, height, NO_PARENT);
+        }
+        self.prepare_level(start_level, width, height);
+
+        self.start_level = start_level;
         self.found_pos = None;
         self.found_cost = None;
+        self.found_all.clear();
 
         self.searches.clear();
-        self.searches.push(DijkstraSearch {
-            cost: self.start_cost,
-            heuristic: 0,
-            pos: self.start_pos,
-        }, ());
-        self.visited[self.start_pos] = 0;
-
-        while let Some((search, _)) = self.searches.pop() {
+        self.binary_heap.clear();
+        self.stats = HeapStats::default();
+        self.last_popped = i64::MIN;
+        for i in 0..self.starts.len() {
+            let (pos, cost) = self.starts[i];
+            if self.visited[start_level][pos] > cost {
+                self.visited[start_level][pos] = cost;
+                self.push(DijkstraSearch {
+                    cost,
+                    heuristic: 0,
+                    pos,
+                    level: start_level,
+                });
+            }
+        }
+
+        while let Some(search) = self.pop() {
             let (x, y) = search.pos;
-            for offset_pos in valid_offsets(self.diagonal, x, y, grid.width, grid.height) {
-                let v = &grid[offset_pos];
-                match check(v, offset_pos) {
-                    DijkstraStep::DeadEnd => {}
-                    DijkstraStep::Found(cost) => {
-                        self.found_pos = Some(offset_pos);
-                        self.found_cost = Some(search.cost + cost);
+            let level = search.level;
+            let parent = (x, y, level);
+
+            for offset_pos in valid_offsets(self.diagonal, x, y, width, height) {
+                let step = check(grid.cell(offset_pos.0, offset_pos.1), offset_pos, level);
+                if self.visit(offset_pos, level, parent, search.cost, step, stop_at_first) {
+                    return;
+                }
+            }
+
+            if !self.portals.is_empty() {
+                for portal in self.portals.from(search.pos) {
+                    let new_level = match change_level(level, portal.level_change, self.level_bounds) {
+                        Some(new_level) => new_level,
+                        None => continue,
+                    };
+                    self.prepare_level(new_level, width, height);
+
+                    let step = match check(grid.cell(portal.to.0, portal.to.1), portal.to, new_level) {
+                        DijkstraStep::DeadEnd => continue,
+                        DijkstraStep::Found(_) => DijkstraStep::Found(portal.cost),
+                        DijkstraStep::Continue(_, heuristic) => DijkstraStep::Continue(portal.cost, heuristic),
+                    };
+                    if self.visit(portal.to, new_level, parent, search.cost, step, stop_at_first) {
                         return;
                     }
-                    DijkstraStep::Continue(cost, heuristic) => {
-                        let new_cost = cost + search.cost;
-                        if self.visited[offset_pos] > new_cost {
-                            self.visited[offset_pos] = new_cost;
-
-                            self.searches.push(DijkstraSearch {
-                                cost: new_cost,
-                                pos: offset_pos,
-                                heuristic,
-                            }, ());
-                        }
-                    }
                 }
             }
         }
     }
 
+    /// Handle the step onto a position, and return true if the search is over.
+    fn visit(&mut self, pos: (usize, usize), level: usize, parent: (usize, usize, usize), base_cost: i64, step: DijkstraStep, stop_at_first: bool) -> bool {
+        let (cost, heuristic) = match step {
+            DijkstraStep::DeadEnd => return false,
+            DijkstraStep::Found(cost) if stop_at_first => {
+                if let Some(parents) = self.parents.as_mut() {
+                    parents[level][pos] = parent;
+                }
+
+                self.found_pos = Some(pos);
+                self.found_cost = Some(base_cost + cost);
+                self.found_level = level;
+                return true;
+            }
+            DijkstraStep::Found(cost) => {
+                if base_cost + cost < self.visited[level][pos] {
+                    self.found_all.push((pos, level, base_cost + cost));
+                }
+
+                (cost, 0)
+            }
+            DijkstraStep::Continue(cost, heuristic) => (cost, heuristic),
+        };
+
+        let new_cost = cost + base_cost;
+        if self.visited[level][pos] > new_cost {
+            self.visited[level][pos] = new_cost;
+            if let Some(parents) = self.parents.as_mut() {
+                parents[level][pos] = parent;
+            }
+
+            self.push(DijkstraSearch {
+                cost: new_cost,
+                pos,
+                level,
+                heuristic,
+            });
+        }
+
+        false
+    }
+
+    fn push(&mut self, search: DijkstraSearch) {
+        if self.use_binary_heap {
+            self.binary_heap.push(search);
+        } else {
+            #[cfg(debug_assertions)]
+            if search.cost + search.heuristic < self.last_popped {
+                panic!(
+                    "Dijkstra stepped to {:?} on level {} with cost {} and heuristic {}, which is below the last expanded {}. Use with_binary_heap for negative costs or inconsistent heuristics.",
+                    search.pos, search.level, search.cost, search.heuristic, self.last_popped,
+                );
+            }
+
+            self.searches.push(search, ());
+        }
+
+        self.stats.pushes += 1;
+        self.stats.max_size = self.stats.max_size.max(self.searches.len() + self.binary_heap.len());
+    }
+
+    fn pop(&mut self) -> Option<DijkstraSearch> {
+        let search = if self.use_binary_heap {
+            self.binary_heap.pop()
+        } else {
+            self.searches.pop().map(|(search, _)| search)
+        }?;
+
+        self.stats.pops += 1;
+        self.last_popped = search.cost + search.heuristic;
+
+        Some(search)
+    }
+
+    fn prepare_level(&mut self, level: usize, width: usize, height: usize) {
+        prepare_level(&mut self.visited, level, width, height, i64::MAX);
+        if let Some(parents) = self.parents.as_mut() {
+            prepare_level(parents, level, width, height, NO_PARENT);
+        }
+    }
+
+    /// Record the cheapest predecessor of every reached position, so that `path` and `path_to`
+    /// can be used after a run.
+    pub fn with_path(mut self) -> Dijkstra {
+        self.parents = Some(Vec::new());
+        self
+    }
+
+    /// Use a binary heap instead of the radix heap.
+    pub fn with_binary_heap(mut self) -> Dijkstra {
+        self.use_binary_heap = true;
+        self
+    }
+
+    /// Add another start position, so that the search begins from all of them at once. When
+    /// several starts share a position, the cheapest one is used.
+    pub fn with_start(mut self, x: usize, y: usize, cost: i64) -> Dijkstra {
+        self.starts.push(((x, y), cost));
+        self
+    }
+
+    /// Add portals to take after searching from their `from` tile. The check is still called on
+    /// the portal's target to see if it's a dead end or found, but the portal's cost is used
+    /// instead of the cost from the check.
+    pub fn with_portals(mut self, portals: impl IntoIterator<Item=Portal>) -> Dijkstra {
+        for portal in portals {
+            self.portals.add(portal);
+        }
+
+        self
+    }
+
+    /// Set the lowest and highest level (inclusive) that can be reached in multilevel runs.
+    /// The default is 0 to 10000.
+    pub fn with_level_bounds(mut self, min: usize, max: usize) -> Dijkstra {
+        self.level_bounds = (min, max);
+        self
+    }
+
+    /// Create a search from the start position. The start is recorded with `start_cost` in
+    /// `visited`, and every step away from it adds to that.
     pub fn new(diagonal: bool, start_x: usize, start_y: usize, start_cost: i64) -> Dijkstra {
         Dijkstra {
             diagonal,
-            start_cost,
 
-            start_pos: (start_x, start_y),
+            starts: vec![((start_x, start_y), start_cost)],
+            start_level: 0,
             found_cost: None,
             found_pos: None,
-            visited: FixedGrid::empty(),
+            found_level: 0,
+            found_all: Vec::new(),
+            visited: vec![FixedGrid::empty()],
+            parents: None,
             searches: RadixHeapMap::new(),
+            binary_heap: BinaryHeap::new(),
+            use_binary_heap: false,
+            last_popped: i64::MIN,
+            stats: HeapStats::default(),
+            portals: PortalTable::default(),
+            level_bounds: DEFAULT_LEVEL_BOUNDS,
+        }
+    }
+}
+
+/// Reset the grids for each level, but drop the ones that are of the wrong size.
+fn reset_levels<V: Copy>(levels: &mut [FixedGrid<V>], width: usize, height: usize, value: V) {
+    for grid in levels.iter_mut() {
+        if grid.width == width && grid.height == height {
+            grid.data.fill(value);
+        } else {
+            *grid = FixedGrid::empty();
         }
     }
 }
 
+fn prepare_level<V: Copy>(levels: &mut Vec<FixedGrid<V>>, level: usize, width: usize, height: usize, value: V) {
+    while levels.len() <= level {
+        levels.push(FixedGrid::empty());
+    }
+    if levels[level].width != width || levels[level].height != height {
+        levels[level] = FixedGrid::new(width, height, value);
+    }
+}
+
+/// Counters of the heap use in a `Dijkstra` run, for tuning heuristics and heap choice.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct HeapStats {
+    pub pushes: usize,
+    pub pops: usize,
+    pub max_size: usize,
+}
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub struct DijkstraSearch {
     pos: (usize, usize),
+    level: usize,
     heuristic: i64,
     cost: i64,
 }
@@ -618,7 +2497,9 @@ impl Ord for DijkstraSearch {
     fn cmp(&self, other: &Self) -> Ordering {
         let a_f_score = self.cost + self.heuristic;
         let b_f_score = other.cost + other.heuristic;
-        b_f_score.cmp(&a_f_score).then_with(|| self.pos.cmp(&other.pos))
+        b_f_score.cmp(&a_f_score)
+            .then_with(|| self.pos.cmp(&other.pos))
+            .then_with(|| self.level.cmp(&other.level))
     }
 }
 
@@ -699,6 +2580,696 @@ pub mod tests {
         assert_eq!(s, 2);
     }
 
+    #[test]
+    pub fn test_bfs_path() {
+        let mut bfs = BFS::<()>::new().with_path();
+        let grid = FixedGrid::<u8>::from_str(&TEST_GRID);
+
+        bfs.run(&grid, 12, 8, false, checker_without_state);
+
+        let path = bfs.path().unwrap();
+        assert_eq!(path.len(), 19);
+        assert_eq!(path.first(), Some(&(12, 8)));
+        assert_eq!(path.last(), Some(&(1, 1)));
+        for w in path.windows(2) {
+            assert_eq!(manhattan_distance(w[0], w[1]), 1);
+            assert_ne!(grid[w[1]], b'#');
+        }
+
+        assert!(BFS::<()>::new().path().is_none());
+    }
+
+    #[test]
+    pub fn test_dijkstra_path() {
+        let grid = FixedGrid::<u8>::from_str("
+19999
+11199
+99119
+99911
+");
+
+        let mut dijkstra = Dijkstra::new(false, 0, 0, 0).with_path();
+        dijkstra.run(&grid, |v, pos| {
+            if pos == (4, 3) {
+                DijkstraStep::Found((*v - b'0') as i64)
+            } else {
+                DijkstraStep::Continue((*v - b'0') as i64, 0)
+            }
+        });
+
+        assert_eq!(dijkstra.found_cost(), Some(7));
+        assert_eq!(dijkstra.path().unwrap(), vec![(0, 0), (0, 1), (1, 1), (2, 1), (2, 2), (3, 2), (3, 3), (4, 3)]);
+        assert_eq!(dijkstra.path_to((2, 1)).unwrap(), vec![(0, 0), (0, 1), (1, 1), (2, 1)]);
+    }
+
+    fn signed_cost(v: &u8, pos: (usize, usize)) -> DijkstraStep {
+        let cost = if *v == b'n' { -1 } else { (*v - b'0') as i64 };
+        if pos == (3, 0) {
+            DijkstraStep::Found(cost)
+        } else {
+            DijkstraStep::Continue(cost, 0)
+        }
+    }
+
+    #[test]
+    pub fn test_dijkstra_binary_heap() {
+        let grid = FixedGrid::<u8>::from_str("1211\n1191\n");
+
+        let mut radix = Dijkstra::new(false, 0, 0, 0);
+        let mut binary = Dijkstra::new(false, 0, 0, 0).with_binary_heap();
+        radix.run(&grid, signed_cost);
+        binary.run(&grid, signed_cost);
+        assert_eq!(radix.found_cost(), Some(4));
+        assert_eq!(binary.found_cost(), Some(4));
+
+        let stats = radix.heap_stats();
+        assert!(stats.pops > 0 && stats.pops <= stats.pushes);
+        assert!(stats.max_size > 0 && stats.max_size <= stats.pushes);
+        assert_eq!(binary.heap_stats().pushes, stats.pushes);
+
+        let negative = FixedGrid::<u8>::from_str("1n11\n9999\n");
+        binary.run(&negative, signed_cost);
+        assert_eq!(binary.found_cost(), Some(1));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "Dijkstra stepped to (1, 0) on level 0 with cost -1")]
+    pub fn test_dijkstra_radix_negative_cost() {
+        let negative = FixedGrid::<u8>::from_str("1n11\n9999\n");
+        Dijkstra::new(false, 0, 0, 0).run(&negative, signed_cost);
+    }
+
+    #[test]
+    pub fn test_dijkstra_run_all() {
+        let grid = FixedGrid::<u8>::from_str(&TEST_GRID);
+
+        let mut dijkstra = Dijkstra::new(false, 12, 8, 0);
+        let found = dijkstra.run_all(&grid, |v, _| match *v {
+            b'#' => DijkstraStep::DeadEnd,
+            b'x' | b'y' => DijkstraStep::Found(1),
+            _ => DijkstraStep::Continue(1, 0),
+        });
+
+        assert_eq!(found.len(), grid.count(b'x') + grid.count(b'y'));
+        assert!(found.windows(2).all(|w| w[0].2 <= w[1].2));
+        assert_eq!(found.iter().find(|(pos, _, _)| *pos == (1, 1)).unwrap().2, 18);
+        let cheapest = found[0];
+        assert_eq!(dijkstra.found_pos(), Some(cheapest.0));
+        assert_eq!(dijkstra.found_cost(), Some(cheapest.2));
+    }
+
+    #[test]
+    pub fn test_dijkstra_distance_map() {
+        let grid = FixedGrid::<u8>::from_str("
+.....
+.###.
+.....
+");
+
+        let mut dijkstra = Dijkstra::new(false, 0, 0, 0).with_start(4, 2, 0).with_path();
+        let distances = dijkstra.distance_map(&grid, |v, _| match *v {
+            b'#' => DijkstraStep::DeadEnd,
+            _ => DijkstraStep::Continue(1, 0),
+        });
+
+        assert_eq!(distances[(0, 0)], 0);
+        assert_eq!(distances[(4, 2)], 0);
+        assert_eq!(distances[(2, 0)], 2);
+        assert_eq!(distances[(4, 0)], 2);
+        assert_eq!(distances[(2, 2)], 2);
+        assert_eq!(distances[(2, 1)], i64::MAX);
+        assert_eq!(dijkstra.found_pos(), None);
+        assert_eq!(dijkstra.path_to((4, 0)).unwrap(), vec![(4, 2), (4, 1), (4, 0)]);
+        assert_eq!(dijkstra.path_to((2, 1)), None);
+
+        let mut dijkstra = Dijkstra::new(false, 0, 0, 5).with_start(4, 2, 1);
+        let distances = dijkstra.distance_map(&grid, |v, _| match *v {
+            b'#' => DijkstraStep::DeadEnd,
+            _ => DijkstraStep::Continue(1, 0),
+        });
+
+        assert_eq!(distances[(0, 0)], 5);
+        assert_eq!(distances[(1, 0)], 6);
+        assert_eq!(distances[(4, 2)], 1);
+        assert_eq!(distances[(3, 2)], 2);
+
+        let mut dijkstra = Dijkstra::new(false, 0, 0, 5).with_start(0, 0, 2);
+        let distances = dijkstra.distance_map(&grid, |v, _| match *v {
+            b'#' => DijkstraStep::DeadEnd,
+            _ => DijkstraStep::Continue(1, 0),
+        });
+
+        assert_eq!(distances[(0, 0)], 2);
+        assert_eq!(distances[(1, 0)], 3);
+        assert_eq!(distances[(4, 2)], 8);
+    }
+
+    const TEST_ROOMS: &str = "
+#######
+#a.#..#
+#..#.b#
+#######
+";
+
+    #[test]
+    pub fn test_bfs_portals() {
+        let grid = FixedGrid::<u8>::from_str(&TEST_ROOMS);
+        let checker = |v: &u8, _: (usize, usize), _: &()| match *v {
+            b'#' => BFSStep::DeadEnd,
+            b'b' => BFSStep::Found(()),
+            _ => BFSStep::Continue(()),
+        };
+
+        assert!(BFS::<()>::new().run(&grid, 1, 1, false, checker).is_none());
+
+        let mut bfs = BFS::<()>::new().with_path().with_portals([Portal::new((2, 2), (4, 1), 1)]);
+        let (_, len, _) = bfs.run(&grid, 1, 1, false, checker).unwrap();
+        assert_eq!(len, 5);
+        assert_eq!(bfs.path().unwrap().len(), 6);
+    }
+
+    #[test]
+    pub fn test_bfs_portal_levels() {
+        let grid = FixedGrid::<u8>::from_str(&TEST_ROOMS);
+        let portals = [
+            Portal::new((2, 2), (4, 1), 1).with_level_change(1),
+            Portal::new((4, 2), (1, 2), 1),
+            Portal::new((1, 1), (5, 2), 0).with_level_change(-1),
+        ];
+        let checker = |v: &u8, _: (usize, usize), level: usize, _: &()| match *v {
+            b'#' => BFSStep::DeadEnd,
+            b'b' if level == 2 => BFSStep::Found(()),
+            _ => BFSStep::Continue(()),
+        };
+
+        let mut bfs = BFS::<()>::new().with_portals(portals);
+        let (_, len, _) = bfs.run_multilevel(&grid, 1, 1, 0, false, checker).unwrap();
+        assert_eq!(len, 9);
+        assert_eq!(bfs.found_level(), Some(2));
+
+        let mut bfs = BFS::<()>::new().with_portals(portals).with_level_bounds(0, 1);
+        assert!(bfs.run_multilevel(&grid, 1, 1, 0, false, checker).is_none());
+    }
+
+    #[test]
+    pub fn test_dijkstra_portals() {
+        let grid = FixedGrid::<u8>::from_str(&TEST_ROOMS);
+        let portal = Portal::new((2, 2), (4, 1), 5).with_level_change(1);
+
+        let mut dijkstra = Dijkstra::new(false, 1, 1, 0).with_path().with_portals([portal]);
+        dijkstra.run_multilevel(&grid, 0, |v, _, level| match *v {
+            b'#' => DijkstraStep::DeadEnd,
+            b'b' if level == 1 => DijkstraStep::Found(1),
+            _ => DijkstraStep::Continue(1, 0),
+        });
+
+        assert_eq!(dijkstra.found_cost(), Some(9));
+        assert_eq!(dijkstra.found_level(), Some(1));
+        assert_eq!(dijkstra.path().unwrap().len(), 6);
+        assert_eq!(dijkstra.visited()[(2, 2)], 2);
+        assert_eq!(dijkstra.visited_level(1).unwrap()[(4, 1)], 7);
+
+        let mut dijkstra = Dijkstra::new(false, 1, 1, 0).with_portals([portal]).with_level_bounds(0, 0);
+        dijkstra.run_multilevel(&grid, 0, |v, _, _| match *v {
+            b'#' => DijkstraStep::DeadEnd,
+            b'b' => DijkstraStep::Found(1),
+            _ => DijkstraStep::Continue(1, 0),
+        });
+        assert_eq!(dijkstra.found_pos(), None);
+
+        let portals = [Portal::new((1, 2), (4, 2), 1), portal];
+        let mut dijkstra = Dijkstra::new(false, 1, 1, 0).with_portals(portals);
+        let found = dijkstra.run_all(&grid, |v, _| match *v {
+            b'#' => DijkstraStep::DeadEnd,
+            b'b' => DijkstraStep::Found(1),
+            _ => DijkstraStep::Continue(1, 0),
+        });
+
+        assert_eq!(found, [((5, 2), 0, 3), ((5, 2), 1, 9)]);
+        assert_eq!(dijkstra.found_level(), Some(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Portal from (2, 2) leads to (7, 1), outside the 7x4 grid")]
+    pub fn test_dijkstra_portal_outside_grid() {
+        let grid = FixedGrid::<u8>::from_str(&TEST_ROOMS);
+        let mut dijkstra = Dijkstra::new(false, 1, 1, 0).with_portals([Portal::new((2, 2), (7, 1), 1)]);
+        dijkstra.run(&grid, |_, _| DijkstraStep::Continue(1, 0));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "(2, 0) is outside the 2x2 view")]
+    pub fn test_get_in_bounds_outside_view() {
+        let grid = FixedGrid::new(4, 4, 0u8);
+        grid.view(1, 1, 2, 2).get_in_bounds(2, 0);
+    }
+
+    #[test]
+    pub fn test_point_graph() {
+        let grid = FixedGrid::<u8>::from_str("
+########################
+#f.D.E.e.C.b.A.@.a.B.c.#
+######################.#
+#d.....................#
+########################
+");
+
+        let points = grid.point_graph(
+            |v| *v == b'@' || v.is_ascii_lowercase(),
+            |v| *v == b'.',
+            |v| v.is_ascii_uppercase(),
+        );
+        let index = |c: u8| points.index_of(grid.find(c).unwrap()).unwrap();
+        let gates = |from: u8, to: u8| -> Vec<u8> {
+            points.path(index(from), index(to)).unwrap().gates.iter().map(|p| grid[*p]).collect()
+        };
+
+        assert_eq!(points.points().len(), 7);
+        assert_eq!(points.distance(index(b'@'), index(b'a')), Some(2));
+        assert_eq!(points.distance(index(b'@'), index(b'b')), Some(4));
+        assert_eq!(points.distance(index(b'b'), index(b'@')), Some(4));
+        assert_eq!(points.distance(index(b'@'), index(b'd')), Some(30));
+        assert_eq!(gates(b'@', b'a'), b"");
+        assert_eq!(gates(b'@', b'b'), b"A");
+        assert_eq!(gates(b'@', b'f'), b"ACED");
+        assert_eq!(gates(b'a', b'c'), b"B");
+        assert_eq!(points.paths_from(index(b'@'))[0].to, index(b'a'));
+
+        let graph = points.to_graph();
+        assert_eq!(graph.len(), 7);
+        assert_eq!(graph.edges().len(), 7 * 6 / 2);
+    }
+
+    const TEST_BASINS: &str = "
+2199943210
+3987894921
+9856789892
+8767896789
+9899965678
+";
+
+    #[test]
+    pub fn test_flood_fill() {
+        let grid = FixedGrid::<u8>::from_str(&TEST_BASINS);
+
+        assert_eq!(grid.flood_fill((0, 0), |v| *v != b'9').len(), 3);
+        assert_eq!(grid.flood_fill((9, 0), |v| *v != b'9').len(), 9);
+        assert_eq!(grid.flood_fill((2, 2), |v| *v != b'9').len(), 14);
+        assert!(grid.flood_fill((2, 0), |v| *v != b'9').is_empty());
+    }
+
+    #[test]
+    pub fn test_label_components() {
+        let grid = FixedGrid::<u8>::from_str(&TEST_BASINS);
+
+        let (labels, components) = grid.label_components(|v| *v != b'9', false);
+        let mut sizes: Vec<usize> = components.iter().map(|c| c.size).collect();
+        sizes.sort_unstable();
+        assert_eq!(sizes, vec![3, 9, 9, 14]);
+        assert_eq!(labels[(0, 0)], 1);
+        assert_eq!(labels[(2, 0)], 0);
+        assert_eq!(components[0], Component { size: 3, min: (0, 0), max: (1, 1) });
+
+        let (_, components) = grid.label_components(|v| *v == b'9', true);
+        assert_eq!(components.len(), 2);
+        assert_eq!(components[1], Component { size: 1, min: (0, 4), max: (0, 4) });
+        assert_eq!(components[0].size + 1, grid.count(b'9'));
+    }
+
+    #[test]
+    pub fn test_bit_grid() {
+        let grid = FixedGrid::<u8>::from_str(&TEST_GRID);
+        let walls = BitGrid::from_grid(&grid, |v| *v == b'#');
+
+        assert_eq!(walls.count(true), grid.count(b'#'));
+        assert_eq!(walls.count(false), grid.width() * grid.height() - grid.count(b'#'));
+        assert_eq!(walls.iter_ones().count(), walls.count_ones());
+        assert!(walls.iter_ones().all(|(x, y)| grid[(x, y)] == b'#'));
+        assert_eq!(walls.get(22, 0), None);
+        assert!(!walls.has(22, 0));
+
+        let mut floor = walls.clone();
+        floor.invert();
+        assert_eq!(floor.count(true), walls.count(false));
+        floor.and(&walls);
+        assert_eq!(floor.count_ones(), 0);
+        floor.or(&walls);
+        floor.xor(&walls);
+        assert_eq!(floor.count_ones(), 0);
+        assert_eq!(walls.to_grid(b'.', b'#').find(b'#'), Some((0, 0)));
+    }
+
+    #[test]
+    pub fn test_bit_grid_shift() {
+        let mut grid = BitGrid::new(130, 3);
+        grid.set(0, 0, true);
+        grid.set(63, 1, true);
+        grid.set(129, 2, true);
+
+        grid.shift(65, 0);
+        assert_eq!(grid.iter_ones().collect::<Vec<_>>(), vec![(65, 0), (128, 1)]);
+
+        grid.shift(-64, 1);
+        assert_eq!(grid.iter_ones().collect::<Vec<_>>(), vec![(1, 1), (64, 2)]);
+
+        grid.shift(-2, -1);
+        assert_eq!(grid.iter_ones().collect::<Vec<_>>(), vec![(62, 1)]);
+
+        grid.fill(true);
+        assert_eq!(grid.count_ones(), 390);
+        assert!(grid.lines().all(|line| line[2] == 0b11));
+    }
+
+    #[test]
+    pub fn test_tiny_grid() {
+        const TINY: TinyGrid<u8, 3, 6> = TinyGrid::parse_bytes(b"#.#\r\n..#\n");
+        const DIGITS: TinyGrid<u8, 2, 4> = TinyGrid::parse_digits(b"12\n34");
+
+        assert_eq!((TINY.width(), TINY.height()), (3, 2));
+        assert_eq!(TINY.line(1), b"..#");
+        assert_eq!(TINY.lines().count(), 2);
+        assert_eq!(Grid::count(&TINY, b'#'), 3);
+        assert_eq!(Grid::find(&TINY, b'.'), Some((1, 0)));
+        assert_eq!(TINY.iter().filter(|(x, _, _)| *x == 2).count(), 2);
+        assert_eq!(TINY.neighbours(0, 0, true).count(), 3);
+        assert_eq!(DIGITS.to_string(), "12\n34\n");
+
+        let fixed = TINY.to_grid();
+        assert_eq!(fixed.data(), TINY.data());
+        assert_eq!(fixed.to_fixed_grid().data(), TINY.to_fixed_grid().data());
+        assert_eq!(TinyGrid::<u8, 3, 6>::from_grid(&fixed).unwrap().data(), TINY.data());
+        assert!(TinyGrid::<u8, 2, 6>::from_grid(&fixed).is_none());
+        assert!(TinyGrid::<u8, 0, 0>::from_grid(&FixedGrid::empty()).is_some());
+        assert_eq!(FixedGrid::parse_str("ab\ncd", |c| c).to_string(), "ab\ncd\n");
+    }
+
+    #[test]
+    pub fn test_draw_line() {
+        let mut grid = FixedGrid::new(6, 4, b'.');
+        grid.draw_line((0, 0), (5, 2), |v| *v = b'#');
+        grid.draw_line((9, 9), (9, 9), |v| *v = b'!');
+        assert_eq!(grid.line(0), b"##....");
+        assert_eq!(grid.line(1), b"..##..");
+        assert_eq!(grid.line(2), b"....##");
+        assert_eq!(grid.count(b'#'), 6);
+
+        let mut counts = FixedGrid::new(4, 4, 0u8);
+        assert!(counts.draw_line_kind((3, 0), (0, 3), LineKind::AxisOrDiagonal, |v| *v += 1));
+        assert!(!counts.draw_line_kind((3, 0), (0, 3), LineKind::Axis, |v| *v += 1));
+        assert!(!counts.draw_line_kind((0, 0), (1, 3), LineKind::AxisOrDiagonal, |v| *v += 1));
+        assert!(counts.draw_line_kind((0, 2), (3, 2), LineKind::Axis, |v| *v += 1));
+        assert_eq!(counts.count(2), 1);
+        assert_eq!(counts[(1, 2)], 2);
+    }
+
+    #[test]
+    pub fn test_draw_shapes() {
+        let mut grid = FixedGrid::new(5, 4, 0u8);
+        grid.draw_polyline(&[(0, 0), (4, 0), (4, 3), (0, 3)], |v| *v += 1);
+        assert_eq!(grid.count(1), 12);
+        assert_eq!(grid.count(2), 0);
+
+        let mut grid = FixedGrid::new(5, 4, 0u8);
+        grid.draw_polyline(&[(0, 0), (4, 0), (4, 3), (0, 3), (0, 0)], |v| *v += 1);
+        assert_eq!(grid.count(1), 14);
+        assert_eq!(grid.count(2), 0);
+
+        let mut grid = FixedGrid::new(5, 4, 0u8);
+        grid.draw_polyline(&[(0, 0), (4, 0), (4, 3), (2, 3), (2, 0)], |v| *v += 1);
+        assert_eq!(grid[(2, 0)], 2);
+        assert_eq!(grid.count(2), 1);
+
+        let mut grid = FixedGrid::new(5, 4, 0u8);
+        grid.draw_rect((3, 2), (1, 0), false, |v| *v += 1);
+        grid.draw_rect((3, 3), (7, 3), true, |v| *v += 1);
+        assert_eq!(grid.line(0), &[0, 1, 1, 1, 0]);
+        assert_eq!(grid.line(1), &[0, 1, 0, 1, 0]);
+        assert_eq!(grid.line(2), &[0, 1, 1, 1, 0]);
+        assert_eq!(grid.line(3), &[0, 0, 0, 1, 1]);
+    }
+
+    #[test]
+    pub fn test_grid_parser() {
+        let grid = FixedGrid::<u8>::from_str("\r\nab\r\ncd\r\n\r\n\n");
+        assert_eq!((grid.width(), grid.height()), (2, 2));
+        assert_eq!(grid.data(), b"abcd");
+
+        let chars = FixedGrid::<char>::parse_str("αβ\nγδ\n", |c| c);
+        assert_eq!((chars.width(), chars.height()), (2, 2));
+        assert_eq!(chars[(1, 1)], 'δ');
+
+        let parser = GridParser::new();
+        assert_eq!(
+            parser.parse_bytes(b"\n###\n#.#\n##\n###\n", |b| b).err(),
+            Some(GridParseError::RaggedLine { line: 4, width: 2, expected: 3 }),
+        );
+        assert_eq!(
+            parser.parse_bytes(b"##\n\n##\n", |b| b).err(),
+            Some(GridParseError::RaggedLine { line: 2, width: 0, expected: 2 }),
+        );
+        assert_eq!(
+            parser.try_parse_bytes(b"\n\n#.\n.x\n", |b, _, _| if b == b'x' { None } else { Some(b) }).err(),
+            Some(GridParseError::UnknownTile { line: 4, column: 2, tile: 'x' }),
+        );
+        assert_eq!(parser.parse_bytes(b"\n\n", |b| b).unwrap().width(), 0);
+
+        let padded = GridParser::new().with_padding(b' ').parse_bytes(b"  A\nBC\n\nDEFG\n", |b| b).unwrap();
+        assert_eq!((padded.width(), padded.height()), (4, 4));
+        assert_eq!(padded.data(), b"  A BC      DEFG");
+    }
+
+    #[test]
+    #[should_panic(expected = "line 3 is 0 wide, expected 2")]
+    pub fn test_from_str_blank_line() {
+        FixedGrid::<u8>::from_str("\n##\n\n##\n");
+    }
+
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    enum Tile {
+        Wall,
+        Floor,
+    }
+
+    impl TryFrom<u8> for Tile {
+        type Error = ();
+
+        fn try_from(v: u8) -> Result<Self, Self::Error> {
+            match v {
+                b'#' => Ok(Tile::Wall),
+                b'.' => Ok(Tile::Floor),
+                _ => Err(()),
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_parse_tiles() {
+        let input = TEST_GRID.as_bytes();
+        let (grid, markers) = FixedGrid::<Tile>::parse_tiles(input, |b| b == b'x' || b == b'y', Tile::Floor).unwrap();
+        let bytes = FixedGrid::<u8>::from_str(TEST_GRID);
+
+        assert_eq!((grid.width(), grid.height()), (bytes.width(), bytes.height()));
+        assert_eq!(grid.count(Tile::Wall), bytes.count(b'#'));
+        assert_eq!(markers[&b'y'], vec![(1, 1)]);
+        assert_eq!(markers[&b'x'].len(), bytes.count(b'x'));
+        assert_eq!(markers[&b'x'][0], (18, 1));
+        assert_eq!(grid[(1, 1)], Tile::Floor);
+
+        let (crlf, _) = FixedGrid::<Tile>::parse_tiles(b"#.\r\n.#\r\n\r\n", |_| false, Tile::Floor).unwrap();
+        assert_eq!(crlf.data(), &[Tile::Wall, Tile::Floor, Tile::Floor, Tile::Wall]);
+
+        assert_eq!(
+            FixedGrid::<Tile>::parse_tiles(b"#.\n#?\n", |_| false, Tile::Floor).err(),
+            Some(GridParseError::UnknownTile { line: 2, column: 2, tile: '?' }),
+        );
+        assert_eq!(
+            FixedGrid::<Tile>::parse_tiles(b"##\n#\n", |_| false, Tile::Floor).err(),
+            Some(GridParseError::RaggedLine { line: 2, width: 1, expected: 2 }),
+        );
+    }
+
+    #[test]
+    pub fn test_grid_trait_searches() {
+        let grid = FixedGrid::<u8>::from_str(TEST_GRID);
+
+        // The bottom left room, seen through a view, with the exit in its top right corner.
+        let view = grid.view(1, 8, 6, 6);
+        assert_eq!(view.offset(), (1, 8));
+        assert_eq!(view.get(6, 0), None);
+        assert_eq!(view.render_with(|v| *v as char).lines().next(), Some("x#.#.."));
+        let mut bfs = BFS::<()>::new();
+        let (_, len, _) = bfs.run(&view, 0, 1, false, |v, pos, _| match (*v, pos) {
+            (b'#', _) => BFSStep::DeadEnd,
+            (_, (5, 0)) => BFSStep::Found(()),
+            _ => BFSStep::Continue(()),
+        }).unwrap();
+        assert_eq!(len, 10);
+
+        let walls = BitGrid::from_grid(&grid, |v| *v == b'#');
+        assert_eq!(walls.render_with(|v| if *v { '#' } else { '.' }), grid.render_with(|v| if *v == b'#' { '#' } else { '.' }));
+        let mut dijkstra = Dijkstra::new(false, 12, 8, 0);
+        dijkstra.run(&walls, |wall, pos| match (*wall, pos) {
+            (true, _) => DijkstraStep::DeadEnd,
+            (_, (1, 1)) => DijkstraStep::Found(1),
+            _ => DijkstraStep::Continue(1, 0),
+        });
+        assert_eq!(dijkstra.found_cost(), Some(18));
+
+        let tiny = TinyGrid::<u8, 3, 9>::parse_bytes(b"..#\n#.#\n#..\n");
+        let mut bfs = BFS::<()>::new();
+        let (_, len, _) = bfs.run(&tiny, 0, 0, false, |v, pos, _| match (*v, pos) {
+            (b'#', _) => BFSStep::DeadEnd,
+            (_, (2, 2)) => BFSStep::Found(()),
+            _ => BFSStep::Continue(()),
+        }).unwrap();
+        assert_eq!(len, 4);
+
+        let mut set = GridSet::new(4, 4, b'.');
+        for x in -8..4 {
+            set.set_signed(x, -1, b'#');
+        }
+        set.set_signed(-2, -1, b'.');
+        set.set_signed(0, 2, b'.');
+        assert_eq!(*set.get_signed(100, 100), b'.');
+        assert_eq!(set.origin(), (-8, -4));
+        assert_eq!((Grid::width(&set), Grid::height(&set)), (12, 8));
+        *set.get_mut(0, 0).unwrap() = b'S';
+        assert_eq!(*set.get_signed(-8, -4), b'S');
+
+        // Walk through the gap in the wall at x = -2, and through chunks that were never set.
+        let mut dijkstra = Dijkstra::new(false, 0, 0, 0);
+        dijkstra.run(&set, |v, pos| match (*v, pos) {
+            (b'#', _) => DijkstraStep::DeadEnd,
+            (_, (0, 7)) => DijkstraStep::Found(1),
+            _ => DijkstraStep::Continue(1, 0),
+        });
+        assert_eq!(dijkstra.found_cost(), Some(19));
+    }
+
+    #[test]
+    pub fn test_distance_field_areas() {
+        let sources = [(1, 1), (1, 6), (8, 3), (3, 4), (5, 5), (8, 9)];
+        let grid = FixedGrid::new(10, 10, ());
+        let field = distance_field(&grid, &sources, |_| true, DistanceMetric::Manhattan);
+
+        let finite: Vec<usize> = (0..sources.len()).filter(|i| !field.touches_edge(*i)).collect();
+        assert_eq!(finite, vec![3, 4]);
+        assert_eq!(field.area(3), 9);
+        assert_eq!(field.area(4), 17);
+        assert_eq!(field.claim(0, 4), Claim::Tie);
+        assert_eq!(field.claim(5, 0), Claim::Tie);
+        assert_eq!(field.distance(9, 0), Some(4));
+
+        let field = distance_field(&grid, &sources[..1], |_| true, DistanceMetric::Chebyshev);
+        assert_eq!(field.distance(9, 7), Some(8));
+        assert_eq!(field.distances().count(1), 8);
+
+        let field = distance_field(&FixedGrid::new(0, 3, ()), &[], |_| true, DistanceMetric::Manhattan);
+        assert!(!field.touches_edge(0));
+    }
+
+    #[test]
+    pub fn test_distance_field_walls() {
+        let grid = FixedGrid::<u8>::from_str("
+#######
+#a..#b#
+#.#.#.#
+#...#.#
+##....#
+#######
+");
+        let sources = [(1, 1), (5, 1)];
+        let field = distance_field(&grid, &sources, |v| *v != b'#', DistanceMetric::Path { diagonal: false });
+
+        assert_eq!(field.distance(5, 4), Some(3));
+        assert_eq!(field.claim(2, 4), Claim::Source(0));
+        assert_eq!(field.claim(3, 4), Claim::Tie);
+        assert_eq!(field.claim(4, 4), Claim::Source(1));
+        assert_eq!(field.distance(3, 4), Some(5));
+        assert_eq!(field.distance(0, 0), None);
+        assert_eq!(field.claim(4, 1), Claim::Unreached);
+
+        let straight = distance_field(&grid, &sources, |v| *v != b'#', DistanceMetric::Manhattan);
+        assert_eq!(straight.distance(3, 1), Some(2));
+        assert_eq!(straight.claim(3, 1), Claim::Tie);
+        assert_eq!(straight.claim(1, 4), Claim::Unreached);
+        assert_eq!(straight.distance(4, 1), None);
+    }
+
+    #[test]
+    pub fn test_sparse_grid() {
+        let mut grid = SparseGrid::new();
+        assert_eq!(grid.bounds(), None);
+        assert_eq!(grid.to_grid(0u8).width(), 0);
+
+        grid.insert(-2, 1, b'a');
+        grid.insert(1_000_000_000, -3, b'b');
+        assert_eq!(grid.insert(-2, 1, b'c'), Some(b'a'));
+        *grid.get_or_insert(0, 0, b'd') += 1;
+        *grid.get_or_insert(0, 0, b'x') += 1;
+        assert_eq!(grid.len(), 3);
+        assert_eq!(grid.get(0, 0), Some(&b'f'));
+        assert_eq!(grid.count(b'c'), 1);
+        assert_eq!(grid.bounds(), Some(((-2, -3), (1_000_000_000, 1))));
+
+        assert_eq!(grid.remove(1_000_000_000, -3), Some(b'b'));
+        assert_eq!(grid.size(), (1_000_000_003, 5));
+        grid.shrink_bounds();
+        assert_eq!(grid.size(), (3, 2));
+
+        let dense = grid.to_grid(b' ');
+        assert_eq!(dense.line(0), b"  f");
+        assert_eq!(dense.line(1), b"c  ");
+
+        let points: SparseGrid<char> = [((3, -1), '#'), ((5, 0), '#'), ((3, -1), '#')].into_iter().collect();
+        assert_eq!(points.len(), 2);
+        assert_eq!(points.to_string(), "#..\n..#\n");
+    }
+
+    #[test]
+    pub fn test_tiled_grid() {
+        let base = FixedGrid::<u8>::from_str("ab\n#c\n");
+        let shift = |v: u8, tx: isize, ty: isize| if v == b'#' { v } else { v.wrapping_add((tx + ty * 3) as u8) };
+        let tiled = TiledGrid::new(&base, 3, 2, shift);
+
+        assert_eq!(tiled.size(), (6, 4));
+        assert_eq!(tiled.tiles(), (3, 2));
+        assert_eq!(tiled.value(1, 1), Some(b'c'));
+        assert_eq!(tiled.value(4, 0), Some(b'c'));
+        assert_eq!(tiled.value(3, 3), Some(b'g'));
+        assert_eq!(tiled.value(2, 3), Some(b'#'));
+        assert_eq!(tiled.value(6, 0), None);
+
+        let fixed = tiled.to_fixed_grid();
+        assert_eq!(fixed.count(b'#'), 6);
+        assert_eq!(fixed.render_with(|v| *v as char).lines().nth(3), Some("#f#g#h"));
+
+        // The walls repeat in every odd row, so the search has to go around them.
+        let mut bfs = BFS::<()>::new();
+        let (v, len, _) = bfs.run(&tiled, 0, 0, false, |v, pos, _| match (v, pos) {
+            (b'#', _) => BFSStep::DeadEnd,
+            (_, (5, 3)) => BFSStep::Found(()),
+            _ => BFSStep::Continue(()),
+        }).unwrap();
+        assert_eq!((v, len), (b'c' + 5, 8));
+
+        let mut dijkstra = Dijkstra::new(false, 0, 0, 0);
+        dijkstra.run(&tiled, |v, pos| match (v, pos) {
+            (b'#', _) => DijkstraStep::DeadEnd,
+            (_, (5, 3)) => DijkstraStep::Found(1),
+            _ => DijkstraStep::Continue(1, 0),
+        });
+        assert_eq!(dijkstra.found_cost(), Some(8));
+        assert_eq!(dijkstra.distance_map(&fixed, |v, _| if *v == b'#' { DijkstraStep::DeadEnd } else { DijkstraStep::Continue(1, 0) })[(5, 3)], 8);
+
+        let infinite = InfiniteTiledGrid::new(&base, shift);
+        assert_eq!(infinite.value(-1, -1), Some(b'c' - 4));
+        assert_eq!(infinite.value(101, 0), Some(b'b' + 50));
+        assert_eq!(InfiniteTiledGrid::new(&FixedGrid::<u8>::empty(), shift).value(0, 0), None);
+    }
+
     fn checker_without_state(v: &u8, _: (usize, usize), _: &()) -> BFSStep<()> {
         match *v {
             b'#' => BFSStep::DeadEnd,
@@ -0,0 +1,282 @@
+use std::ops::{Index, IndexMut};
+use crate::grid::FixedGrid;
+
+/// Dense grid with any number of dimensions, addressed by signed positions. The origin is the
+/// lowest position in the grid, so the grid can be grown in every direction.
+#[derive(Clone, Eq, Hash, PartialEq)]
+pub struct FixedGridN<T, const N: usize> {
+    data: Vec<T>,
+    dims: [usize; N],
+    origin: [isize; N],
+}
+
+pub type FixedGrid3<T> = FixedGridN<T, 3>;
+
+impl<T, const N: usize> FixedGridN<T, N> {
+    pub fn dims(&self) -> [usize; N] {
+        self.dims
+    }
+
+    pub fn origin(&self) -> [isize; N] {
+        self.origin
+    }
+
+    /// Get the position past the highest one in the grid along every axis.
+    pub fn end(&self) -> [isize; N] {
+        let mut end = self.origin;
+        for (e, d) in end.iter_mut().zip(self.dims.iter()) {
+            *e += *d as isize;
+        }
+
+        end
+    }
+
+    pub fn data(&self) -> &[T] {
+        &self.data
+    }
+
+    pub fn contains(&self, pos: [isize; N]) -> bool {
+        self.index_of(pos).is_some()
+    }
+
+    pub fn get(&self, pos: [isize; N]) -> Option<&T> {
+        self.index_of(pos).map(|i| &self.data[i])
+    }
+
+    pub fn get_mut(&mut self, pos: [isize; N]) -> Option<&mut T> {
+        self.index_of(pos).map(move |i| &mut self.data[i])
+    }
+
+    /// Iterate over every position, with the first axis changing the fastest.
+    pub fn iter(&self) -> impl Iterator<Item=([isize; N], &T)> {
+        self.data.iter().enumerate().map(move |(i, v)| (self.pos_of(i), v))
+    }
+
+    /// Iterate over the neighbours inside the grid. Without diagonals that is the 2N that share
+    /// a face (6 in 3D), and with diagonals all 3^N - 1 of them (26 in 3D).
+    pub fn neighbours(&self, pos: [isize; N], diagonal: bool) -> impl Iterator<Item=[isize; N]> + '_ {
+        neighbour_offsets::<N>(diagonal)
+            .map(move |offset| {
+                let mut pos2 = pos;
+                for (p, o) in pos2.iter_mut().zip(offset.iter()) {
+                    *p += *o;
+                }
+
+                pos2
+            })
+            .filter(move |pos2| self.contains(*pos2))
+    }
+
+    fn index_of(&self, pos: [isize; N]) -> Option<usize> {
+        let mut index = 0;
+        for axis in (0..N).rev() {
+            let v = pos[axis] - self.origin[axis];
+            if v < 0 || v as usize >= self.dims[axis] {
+                return None;
+            }
+
+            index = (index * self.dims[axis]) + v as usize;
+        }
+
+        Some(index)
+    }
+
+    fn pos_of(&self, mut index: usize) -> [isize; N] {
+        let mut pos = self.origin;
+        for (p, d) in pos.iter_mut().zip(self.dims.iter()) {
+            *p += (index % *d) as isize;
+            index /= *d;
+        }
+
+        pos
+    }
+}
+
+impl<T, const N: usize> FixedGridN<T, N> where T: Copy {
+    pub fn set(&mut self, pos: [isize; N], v: T) {
+        match self.index_of(pos) {
+            Some(i) => self.data[i] = v,
+            None => panic!("Set out of bounds {:?}", pos),
+        }
+    }
+
+    /// Get a copy grown by `by` cells in both directions of every axis, which suits puzzles
+    /// where the active area grows every step.
+    pub fn expanded(&self, by: usize, fill: T) -> FixedGridN<T, N> {
+        let mut dims = self.dims;
+        let mut origin = self.origin;
+        for axis in 0..N {
+            dims[axis] += by * 2;
+            origin[axis] -= by as isize;
+        }
+
+        let mut grid = FixedGridN::new(dims, origin, fill);
+        for (pos, v) in self.iter() {
+            grid.set(pos, *v);
+        }
+
+        grid
+    }
+
+    /// Get the plane spanned by two axes that goes through `at`, with `x_axis` along the width
+    /// and `y_axis` along the height of the result.
+    pub fn plane(&self, x_axis: usize, y_axis: usize, at: [isize; N]) -> FixedGrid<T> {
+        let mut data = Vec::with_capacity(self.dims[x_axis] * self.dims[y_axis]);
+        let mut pos = at;
+        for y in 0..self.dims[y_axis] {
+            for x in 0..self.dims[x_axis] {
+                pos[x_axis] = self.origin[x_axis] + x as isize;
+                pos[y_axis] = self.origin[y_axis] + y as isize;
+                data.push(self[pos]);
+            }
+        }
+
+        FixedGrid::from(self.dims[x_axis], self.dims[y_axis], data)
+    }
+
+    pub fn new(dims: [usize; N], origin: [isize; N], def: T) -> FixedGridN<T, N> {
+        FixedGridN {
+            data: vec![def; dims.iter().product()],
+            dims,
+            origin,
+        }
+    }
+}
+
+impl<T> FixedGridN<T, 3> where T: Copy {
+    /// Get the 2D slice at `index` along the axis. The other two axes keep their order, so the
+    /// slice of the z axis has x along the width and y along the height.
+    pub fn slice(&self, axis: usize, index: isize) -> FixedGrid<T> {
+        let (x_axis, y_axis) = match axis {
+            0 => (1, 2),
+            1 => (0, 2),
+            2 => (0, 1),
+            _ => panic!("Axis {} out of bounds", axis),
+        };
+
+        let mut at = self.origin;
+        at[axis] = index;
+
+        self.plane(x_axis, y_axis, at)
+    }
+
+    /// Make a grid from a 2D grid placed at z = 0, with the origin at (0, 0, 0).
+    pub fn from_2d(grid: &FixedGrid<T>) -> FixedGrid3<T> {
+        FixedGridN {
+            data: grid.data().to_vec(),
+            dims: [grid.width(), grid.height(), 1],
+            origin: [0, 0, 0],
+        }
+    }
+}
+
+impl<T, const N: usize> FixedGridN<T, N> where T: Eq {
+    pub fn count(&self, v: T) -> usize {
+        self.data.iter().filter(|v2| **v2 == v).count()
+    }
+
+    pub fn find(&self, v: T) -> Option<[isize; N]> {
+        self.data.iter().position(|v2| *v2 == v).map(|i| self.pos_of(i))
+    }
+}
+
+impl<T, const N: usize> Index<[isize; N]> for FixedGridN<T, N> {
+    type Output = T;
+
+    fn index(&self, pos: [isize; N]) -> &Self::Output {
+        self.get(pos).unwrap()
+    }
+}
+
+impl<T, const N: usize> IndexMut<[isize; N]> for FixedGridN<T, N> {
+    fn index_mut(&mut self, pos: [isize; N]) -> &mut Self::Output {
+        self.get_mut(pos).unwrap()
+    }
+}
+
+/// Iterate over the offsets to the neighbours of a position, without allocating.
+pub fn neighbour_offsets<const N: usize>(diagonal: bool) -> impl Iterator<Item=[isize; N]> {
+    (0..3usize.pow(N as u32))
+        .map(|mut i| {
+            let mut offset = [0isize; N];
+            for o in offset.iter_mut() {
+                *o = (i % 3) as isize - 1;
+                i /= 3;
+            }
+
+            offset
+        })
+        .filter(move |offset| {
+            let moved = offset.iter().filter(|o| **o != 0).count();
+            moved == 1 || (diagonal && moved > 1)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conway_cubes<const N: usize>(initial: &FixedGrid<u8>, steps: usize) -> usize {
+        let mut dims = [1; N];
+        dims[0] = initial.width();
+        dims[1] = initial.height();
+
+        let mut grid = FixedGridN::new(dims, [0; N], false);
+        for (x, y, v) in initial.iter() {
+            let mut pos = [0; N];
+            pos[0] = x as isize;
+            pos[1] = y as isize;
+            grid.set(pos, *v == b'#');
+        }
+
+        for _ in 0..steps {
+            let current = grid.expanded(1, false);
+            let mut next = current.clone();
+            for (pos, active) in current.iter() {
+                let count = current.neighbours(pos, true).filter(|p| current[*p]).count();
+                next.set(pos, count == 3 || (*active && count == 2));
+            }
+
+            grid = next;
+        }
+
+        grid.count(true)
+    }
+
+    #[test]
+    fn test_conway_cubes() {
+        let initial = FixedGrid::<u8>::from_str(".#.\n..#\n###\n");
+
+        assert_eq!(conway_cubes::<3>(&initial, 6), 112);
+        assert_eq!(conway_cubes::<4>(&initial, 1), 29);
+    }
+
+    #[test]
+    fn test_grid3() {
+        let mut grid = FixedGrid3::new([3, 4, 5], [-1, -2, -3], 0u8);
+        assert_eq!(grid.end(), [2, 2, 2]);
+        assert_eq!(grid.neighbours([0, 0, 0], false).count(), 6);
+        assert_eq!(grid.neighbours([0, 0, 0], true).count(), 26);
+        assert_eq!(grid.neighbours([-1, -2, -3], true).count(), 7);
+        assert_eq!(grid.get([2, 0, 0]), None);
+
+        grid.set([1, -2, 0], 5);
+        grid[[-1, 1, 0]] = 7;
+        assert_eq!(grid.find(7), Some([-1, 1, 0]));
+        assert_eq!(grid.count(0), 58);
+        assert_eq!(grid.iter().filter(|(_, v)| **v != 0).count(), 2);
+
+        let z = grid.slice(2, 0);
+        assert_eq!((z.width(), z.height()), (3, 4));
+        assert_eq!(z[(2, 0)], 5);
+        assert_eq!(z[(0, 3)], 7);
+
+        let x = grid.slice(0, 1);
+        assert_eq!((x.width(), x.height()), (4, 5));
+        assert_eq!(x[(0, 3)], 5);
+
+        let flat = FixedGrid3::from_2d(&FixedGrid::<u8>::from_str("ab\ncd\n"));
+        assert_eq!(flat[[1, 1, 0]], b'd');
+        assert_eq!(flat.slice(2, 0).data(), b"abcd");
+    }
+}
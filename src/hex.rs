@@ -0,0 +1,370 @@
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+use rustc_hash::{FxHashMap, FxHashSet};
+use crate::automaton::Neighbourhood;
+
+/// Hex position in axial coordinates, where `q` is the column and `r` the row. The third
+/// cube coordinate is implied by q + r + s = 0.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Axial {
+    pub q: i32,
+    pub r: i32,
+}
+
+/// Hex position in cube coordinates, where q + r + s is always 0.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Cube {
+    pub q: i32,
+    pub r: i32,
+    pub s: i32,
+}
+
+/// The six directions in counter-clockwise order, starting with the one that only moves along
+/// `q`. Which compass names they have depends on the `Layout`.
+pub const DIRECTIONS: [Axial; 6] = [
+    Axial::new(1, 0),
+    Axial::new(1, -1),
+    Axial::new(0, -1),
+    Axial::new(-1, 0),
+    Axial::new(-1, 1),
+    Axial::new(0, 1),
+];
+
+impl Axial {
+    pub const fn new(q: i32, r: i32) -> Axial {
+        Axial { q, r }
+    }
+
+    pub fn s(&self) -> i32 {
+        -self.q - self.r
+    }
+
+    pub fn cube(&self) -> Cube {
+        Cube { q: self.q, r: self.r, s: self.s() }
+    }
+
+    /// Get the amount of steps to the origin.
+    pub fn length(&self) -> i32 {
+        self.cube().length()
+    }
+
+    pub fn distance(&self, other: &Axial) -> i32 {
+        (*self - *other).length()
+    }
+
+    /// Get the neighbour in one of the `DIRECTIONS`.
+    pub fn neighbour(&self, direction: usize) -> Axial {
+        *self + DIRECTIONS[direction]
+    }
+
+    pub fn neighbours(&self) -> impl Iterator<Item=Axial> + use<> {
+        let pos = *self;
+        DIRECTIONS.iter().map(move |d| pos + *d)
+    }
+
+    /// Iterate over the 6 * radius positions at exactly `radius` steps away, or just this one
+    /// for radius 0.
+    pub fn ring(&self, radius: u32) -> impl Iterator<Item=Axial> + use<> {
+        let radius = radius as i32;
+        let start = *self + DIRECTIONS[4] * radius;
+        let steps = if radius == 0 { 1 } else { 6 * radius };
+
+        (0..steps).scan(start, move |pos, i| {
+            let current = *pos;
+            if radius > 0 {
+                *pos += DIRECTIONS[(i / radius) as usize];
+            }
+
+            Some(current)
+        })
+    }
+
+    /// Iterate over every position within `radius` steps, ring by ring from the center out.
+    pub fn spiral(&self, radius: u32) -> impl Iterator<Item=Axial> + use<> {
+        let pos = *self;
+        (0..=radius).flat_map(move |r| pos.ring(r))
+    }
+}
+
+impl Cube {
+    pub fn new(q: i32, r: i32, s: i32) -> Cube {
+        assert_eq!(q + r + s, 0, "Cube coordinates must add up to zero");
+
+        Cube { q, r, s }
+    }
+
+    pub fn axial(&self) -> Axial {
+        Axial::new(self.q, self.r)
+    }
+
+    pub fn length(&self) -> i32 {
+        self.q.abs().max(self.r.abs()).max(self.s.abs())
+    }
+
+    pub fn distance(&self, other: &Cube) -> i32 {
+        Cube { q: self.q - other.q, r: self.r - other.r, s: self.s - other.s }.length()
+    }
+}
+
+impl From<Cube> for Axial {
+    fn from(cube: Cube) -> Self {
+        cube.axial()
+    }
+}
+
+impl From<Axial> for Cube {
+    fn from(axial: Axial) -> Self {
+        axial.cube()
+    }
+}
+
+impl Add for Axial {
+    type Output = Axial;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Axial::new(self.q + rhs.q, self.r + rhs.r)
+    }
+}
+
+impl Sub for Axial {
+    type Output = Axial;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Axial::new(self.q - rhs.q, self.r - rhs.r)
+    }
+}
+
+impl Mul<i32> for Axial {
+    type Output = Axial;
+
+    fn mul(self, rhs: i32) -> Self::Output {
+        Axial::new(self.q * rhs, self.r * rhs)
+    }
+}
+
+impl Neg for Axial {
+    type Output = Axial;
+
+    fn neg(self) -> Self::Output {
+        Axial::new(-self.q, -self.r)
+    }
+}
+
+impl AddAssign for Axial {
+    fn add_assign(&mut self, rhs: Self) {
+        self.q += rhs.q;
+        self.r += rhs.r;
+    }
+}
+
+impl SubAssign for Axial {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.q -= rhs.q;
+        self.r -= rhs.r;
+    }
+}
+
+/// Orientation of the hexes, which decides the compass names of the directions.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Layout {
+    /// Pointy side up, so the rows are straight and there is east and west.
+    Pointy,
+    /// Flat side up, so the columns are straight and there is north and south.
+    Flat,
+}
+
+impl Layout {
+    /// Get the names of the `DIRECTIONS` in this layout.
+    pub fn names(&self) -> [&'static str; 6] {
+        match self {
+            Layout::Pointy => ["e", "ne", "nw", "w", "sw", "se"],
+            Layout::Flat => ["se", "ne", "n", "nw", "sw", "s"],
+        }
+    }
+
+    pub fn direction(&self, name: &str) -> Option<Axial> {
+        self.names().iter().position(|n| *n == name).map(|i| DIRECTIONS[i])
+    }
+
+    /// Parse a list of steps, which may be separated by commas or whitespace, or not at all.
+    pub fn parse_steps(&self, input: &str) -> Option<Vec<Axial>> {
+        let mut steps = Vec::new();
+        let mut rest = input.trim_start_matches(|c: char| c == ',' || c.is_whitespace());
+        while !rest.is_empty() {
+            let len = if rest.get(..2).and_then(|s| self.direction(s)).is_some() { 2 } else { 1 };
+            steps.push(self.direction(rest.get(..len)?)?);
+            rest = rest[len..].trim_start_matches(|c: char| c == ',' || c.is_whitespace());
+        }
+
+        Some(steps)
+    }
+}
+
+/// Sparse map of hexes, where every position that is not stored has the default value.
+///
+/// It can be stepped like an `Automaton` with the same rules. The neighbourhood of a hex fits
+/// a 3x3 block in axial coordinates with the (-1, -1) and (1, 1) corners left out, so the
+/// rule only sees the six real neighbours.
+#[derive(Clone)]
+pub struct HexMap<T> {
+    cells: FxHashMap<Axial, T>,
+    default: T,
+}
+
+impl<T> HexMap<T> where T: Copy + PartialEq {
+    pub fn get(&self, pos: Axial) -> T {
+        self.cells.get(&pos).copied().unwrap_or(self.default)
+    }
+
+    /// Set the value of a hex. Setting the default value removes it from the map.
+    pub fn set(&mut self, pos: Axial, v: T) {
+        if v == self.default {
+            self.cells.remove(&pos);
+        } else {
+            self.cells.insert(pos, v);
+        }
+    }
+
+    pub fn default_value(&self) -> T {
+        self.default
+    }
+
+    /// Get the amount of hexes that are not the default value.
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    /// Iterate over the hexes that are not the default value, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item=(Axial, T)> + '_ {
+        self.cells.iter().map(|(pos, v)| (*pos, *v))
+    }
+
+    /// Count the stored hexes with the value, so this is 0 for the default value.
+    pub fn count(&self, v: T) -> usize {
+        self.cells.values().filter(|v2| **v2 == v).count()
+    }
+
+    /// Iterate over the neighbours that pass the filter, which is what the `Search`
+    /// successors usually are.
+    pub fn neighbours_where<'a>(&'a self, pos: Axial, filter: impl Fn(T) -> bool + 'a) -> impl Iterator<Item=Axial> + 'a {
+        pos.neighbours().filter(move |n| filter(self.get(*n)))
+    }
+
+    pub fn neighbourhood(&self, pos: Axial) -> Neighbourhood<T> {
+        let mut cells = [None; 9];
+        for (i, cell) in cells.iter_mut().enumerate() {
+            if i != 0 && i != 8 {
+                *cell = Some(self.get(pos + Axial::new((i % 3) as i32 - 1, (i / 3) as i32 - 1)));
+            }
+        }
+
+        Neighbourhood::new(cells)
+    }
+
+    /// Apply the rule to every hex at once, and return whether anything changed. Only stored
+    /// hexes and their neighbours are visited, so the rule must keep a default hex with only
+    /// default neighbours as it is.
+    pub fn step(&mut self, mut rule: impl FnMut(T, &Neighbourhood<T>) -> T) -> bool {
+        let mut candidates = FxHashSet::default();
+        for pos in self.cells.keys() {
+            candidates.insert(*pos);
+            candidates.extend(pos.neighbours());
+        }
+
+        let mut next = FxHashMap::default();
+        let mut changed = false;
+        for pos in candidates {
+            let v = self.get(pos);
+            let new_v = rule(v, &self.neighbourhood(pos));
+            if new_v != v {
+                changed = true;
+            }
+            if new_v != self.default {
+                next.insert(pos, new_v);
+            }
+        }
+
+        self.cells = next;
+
+        changed
+    }
+
+    pub fn new(default: T) -> HexMap<T> {
+        HexMap {
+            cells: FxHashMap::default(),
+            default,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::Search;
+
+    #[test]
+    fn test_coordinates() {
+        let a = Axial::new(2, -3);
+        assert_eq!(a.cube(), Cube::new(2, -3, 1));
+        assert_eq!(Axial::from(a.cube()), a);
+        assert_eq!(a.length(), 3);
+        assert_eq!(a.distance(&Axial::new(-1, 0)), 3);
+        assert_eq!(Cube::new(1, 1, -2).distance(&Cube::new(-1, 0, 1)), 3);
+        assert!(a.neighbours().all(|n| n.distance(&a) == 1));
+
+        let origin = Axial::default();
+        assert_eq!(origin.ring(0).collect::<Vec<_>>(), vec![origin]);
+        let ring: Vec<Axial> = origin.ring(2).collect();
+        assert_eq!(ring.len(), 12);
+        assert!(ring.iter().all(|p| p.length() == 2));
+        assert_eq!(ring.iter().collect::<FxHashSet<_>>().len(), 12);
+        assert_eq!(origin.spiral(3).count(), 37);
+        assert_eq!(a.spiral(3).filter(|p| p.distance(&a) <= 3).count(), 37);
+    }
+
+    #[test]
+    fn test_parse_steps() {
+        let walk = |layout: Layout, input: &str| layout.parse_steps(input)
+            .map(|steps| steps.into_iter().fold(Axial::default(), |a, b| a + b));
+
+        assert_eq!(walk(Layout::Flat, "ne,ne,ne").map(|p| p.length()), Some(3));
+        assert_eq!(walk(Layout::Flat, "ne,ne,sw,sw").map(|p| p.length()), Some(0));
+        assert_eq!(walk(Layout::Flat, "ne,ne,s,s").map(|p| p.length()), Some(2));
+        assert_eq!(walk(Layout::Flat, "se,sw,se,sw,sw\n").map(|p| p.length()), Some(3));
+
+        assert_eq!(walk(Layout::Pointy, "esew"), Some(Axial::new(0, 1)));
+        assert_eq!(walk(Layout::Pointy, "nwwswee"), Some(Axial::default()));
+        assert_eq!(Layout::Pointy.parse_steps("enx"), None);
+        assert_eq!(Layout::Flat.parse_steps("e"), None);
+        assert_eq!(Layout::Pointy.parse_steps("nö"), None);
+        assert_eq!(Layout::Flat.parse_steps("ö"), None);
+    }
+
+    #[test]
+    fn test_hex_map() {
+        let mut map = HexMap::new(false);
+        map.set(Axial::new(0, 0), true);
+        map.set(Axial::new(1, 0), true);
+        map.set(Axial::new(5, 5), false);
+        assert_eq!(map.len(), 2);
+
+        // Flipping tiles: black ones need one or two black neighbours to stay, and white ones
+        // turn black with exactly two. Two neighbours share exactly two neighbours.
+        let rule = |black: bool, n: &Neighbourhood<bool>| matches!((black, n.count(true)), (true, 1..=2) | (false, 2));
+        assert!(map.step(rule));
+        assert_eq!(map.count(true), 4);
+        assert_eq!(map.neighbourhood(Axial::new(0, 0)).neighbours().count(), 6);
+
+        let mut walls = HexMap::new(b'.');
+        for pos in Axial::new(0, 0).ring(2).filter(|p| *p != Axial::new(-2, 2)) {
+            walls.set(pos, b'#');
+        }
+
+        let mut search = Search::new();
+        let res = search.bfs(Axial::new(0, 0), |p| walls.neighbours_where(*p, |v| v != b'#').collect::<Vec<_>>(), |p| p.length() == 4);
+        assert_eq!(res.map(|(_, steps)| steps), Some(4));
+    }
+}
@@ -7,4 +7,14 @@ pub mod parsers;
 pub mod parser;
 pub mod permutation;
 pub mod matrix;
-pub mod octree;
\ No newline at end of file
+pub mod octree;
+pub mod graph;
+pub mod search;
+pub mod automaton;
+pub mod cycle;
+pub mod geom;
+pub mod gridn;
+pub mod ocr;
+pub mod prefix;
+pub mod hex;
+pub mod render;
\ No newline at end of file
@@ -0,0 +1,258 @@
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::ops::Range;
+use crate::grid::Grid;
+
+/// The block letter alphabets that puzzle answers are drawn in.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Font {
+    /// 4x6 letters with a blank column between them.
+    Small,
+    /// 6x10 letters with two blank columns between them.
+    Large,
+}
+
+impl Font {
+    pub fn height(&self) -> usize {
+        match self {
+            Font::Small => 6,
+            Font::Large => 10,
+        }
+    }
+
+    /// Pick the smallest font the text fits in.
+    pub fn detect(height: usize) -> Option<Font> {
+        match height {
+            0..=6 => Some(Font::Small),
+            7..=10 => Some(Font::Large),
+            _ => None,
+        }
+    }
+
+    fn glyphs(&self) -> &'static [(char, &'static [&'static str])] {
+        match self {
+            Font::Small => SMALL_GLYPHS,
+            Font::Large => LARGE_GLYPHS,
+        }
+    }
+}
+
+/// Problems found while reading text. Columns are the grid columns the glyph covers.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum OcrError {
+    TooTall { height: usize, font: Font },
+    UnknownGlyphs { columns: Vec<Range<usize>>, partial: String },
+}
+
+impl Display for OcrError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            OcrError::TooTall { height, font } => {
+                write!(f, "text is {} rows tall, {:?} font is {}", height, font, font.height())
+            }
+            OcrError::UnknownGlyphs { columns, partial } => {
+                write!(f, "unrecognised glyphs in \"{}\" at columns ", partial)?;
+                for (i, range) in columns.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}..{}", range.start, range.end)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for OcrError {}
+
+/// Read the text drawn with `on` cells, picking the font from the height of the text. Blank
+/// rows above and below the text are ignored.
+pub fn read<G: Grid>(grid: &G, on: G::Item) -> Result<String, OcrError> where G::Item: PartialEq {
+    let height = match lit_rows(grid, &on) {
+        Some(rows) => rows.len(),
+        None => return Ok(String::new()),
+    };
+
+    match Font::detect(height) {
+        Some(font) => read_with_font(grid, on, font),
+        None => Err(OcrError::TooTall { height, font: Font::Large }),
+    }
+}
+
+/// Read the text drawn with `on` cells in the font. Glyphs are split on blank columns, so
+/// the spacing between them does not matter. Unknown glyphs are `?` in the partial text of
+/// the error.
+pub fn read_with_font<G: Grid>(grid: &G, on: G::Item, font: Font) -> Result<String, OcrError> where G::Item: PartialEq {
+    let rows = match lit_rows(grid, &on) {
+        Some(rows) => rows,
+        None => return Ok(String::new()),
+    };
+    if rows.len() > font.height() {
+        return Err(OcrError::TooTall { height: rows.len(), font });
+    }
+
+    let columns: Vec<u16> = (0..grid.width())
+        .map(|x| rows.clone()
+            .filter(|y| grid.get(x, *y) == Some(&on))
+            .fold(0, |mask, y| mask | (1 << (y - rows.start))))
+        .collect();
+
+    let mut text = String::new();
+    let mut unknown = Vec::new();
+    let mut x = 0;
+    while x < columns.len() {
+        if columns[x] == 0 {
+            x += 1;
+            continue;
+        }
+
+        let start = x;
+        while x < columns.len() && columns[x] != 0 {
+            x += 1;
+        }
+
+        match font.glyphs().iter().find(|(_, rows)| glyph_columns(rows) == columns[start..x]) {
+            Some((c, _)) => text.push(*c),
+            None => {
+                text.push('?');
+                unknown.push(start..x);
+            }
+        }
+    }
+
+    if unknown.is_empty() {
+        Ok(text)
+    } else {
+        Err(OcrError::UnknownGlyphs { columns: unknown, partial: text })
+    }
+}
+
+fn lit_rows<G: Grid>(grid: &G, on: &G::Item) -> Option<Range<usize>> where G::Item: PartialEq {
+    let lit = |y: &usize| (0..grid.width()).any(|x| grid.get(x, *y) == Some(on));
+    let first = (0..grid.height()).find(lit)?;
+    let last = (0..grid.height()).rev().find(lit)?;
+
+    Some(first..last + 1)
+}
+
+/// Get the columns of a glyph as bit masks with the top row in the lowest bit, without the
+/// blank columns around it.
+fn glyph_columns(rows: &[&str]) -> Vec<u16> {
+    let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    let mut columns: Vec<u16> = (0..width)
+        .map(|x| rows.iter().enumerate()
+            .filter(|(_, row)| row.as_bytes().get(x) == Some(&b'#'))
+            .fold(0, |mask, (y, _)| mask | (1 << y)))
+        .collect();
+
+    while columns.last() == Some(&0) {
+        columns.pop();
+    }
+    let leading = columns.iter().take_while(|c| **c == 0).count();
+    columns.drain(..leading);
+
+    columns
+}
+
+const SMALL_GLYPHS: &[(char, &[&str])] = &[
+    ('A', &[".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', &["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', &[".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', &["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', &["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', &[".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', &["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', &["###", ".#.", ".#.", ".#.", ".#.", "###"]),
+    ('J', &["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', &["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', &["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', &[".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', &["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', &["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', &[".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', &["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Y', &["#...#", "#...#", ".#.#.", "..#..", "..#..", "..#.."]),
+    ('Z', &["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+const LARGE_GLYPHS: &[(char, &[&str])] = &[
+    ('A', &["..##..", ".#..#.", "#....#", "#....#", "#....#", "######", "#....#", "#....#", "#....#", "#....#"]),
+    ('B', &["#####.", "#....#", "#....#", "#....#", "#####.", "#....#", "#....#", "#....#", "#....#", "#####."]),
+    ('C', &[".####.", "#....#", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#....#", ".####."]),
+    ('E', &["######", "#.....", "#.....", "#.....", "#####.", "#.....", "#.....", "#.....", "#.....", "######"]),
+    ('F', &["######", "#.....", "#.....", "#.....", "#####.", "#.....", "#.....", "#.....", "#.....", "#....."]),
+    ('G', &[".####.", "#....#", "#.....", "#.....", "#.....", "#..###", "#....#", "#....#", "#...##", ".###.#"]),
+    ('H', &["#....#", "#....#", "#....#", "#....#", "######", "#....#", "#....#", "#....#", "#....#", "#....#"]),
+    ('J', &["...###", "....#.", "....#.", "....#.", "....#.", "....#.", "....#.", "#...#.", "#...#.", ".###.."]),
+    ('K', &["#....#", "#...#.", "#..#..", "#.#...", "##....", "##....", "#.#...", "#..#..", "#...#.", "#....#"]),
+    ('L', &["#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "######"]),
+    ('N', &["#....#", "##...#", "##...#", "#.#..#", "#.#..#", "#..#.#", "#..#.#", "#...##", "#...##", "#....#"]),
+    ('P', &["#####.", "#....#", "#....#", "#....#", "#####.", "#.....", "#.....", "#.....", "#.....", "#....."]),
+    ('R', &["#####.", "#....#", "#....#", "#....#", "#####.", "#..#..", "#...#.", "#...#.", "#....#", "#....#"]),
+    ('X', &["#....#", "#....#", ".#..#.", ".#..#.", "..##..", "..##..", ".#..#.", ".#..#.", "#....#", "#....#"]),
+    ('Z', &["######", ".....#", ".....#", "....#.", "...#..", "..#...", ".#....", "#.....", "#.....", "######"]),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::{BitGrid, FixedGrid};
+
+    /// Draw the text with `spacing` blank columns between the glyphs.
+    fn draw(font: Font, text: &str, spacing: usize) -> FixedGrid<u8> {
+        let mut rows = vec![String::new(); font.height()];
+        for c in text.chars() {
+            let (_, glyph) = font.glyphs().iter().find(|(c2, _)| *c2 == c).unwrap();
+            for (row, line) in rows.iter_mut().zip(glyph.iter()) {
+                row.push_str(line);
+                row.push_str(&".".repeat(spacing));
+            }
+        }
+
+        FixedGrid::<u8>::from_str(&rows.join("\n"))
+    }
+
+    #[test]
+    fn test_read_alphabets() {
+        for font in [Font::Small, Font::Large] {
+            let alphabet: String = font.glyphs().iter().map(|(c, _)| *c).collect();
+            let grid = draw(font, &alphabet, 2);
+
+            assert_eq!(read(&grid, b'#'), Ok(alphabet.clone()));
+            assert_eq!(read_with_font(&grid, b'#', font), Ok(alphabet));
+        }
+    }
+
+    #[test]
+    fn test_read_output() {
+        // Folded paper output, with no blank column after the last glyph.
+        let grid = FixedGrid::<u8>::from_str("
+
+#### #  #
+   # #  #
+  #  ####
+ #   #  #
+#    #  #
+#### #  #
+");
+        assert_eq!(read(&grid, b'#'), Ok(String::from("ZH")));
+
+        let bits = BitGrid::from_grid(&draw(Font::Large, "HXN", 2), |v| *v == b'#');
+        assert_eq!(read(&bits, true), Ok(String::from("HXN")));
+        assert_eq!(read_with_font(&bits, true, Font::Small), Err(OcrError::TooTall { height: 10, font: Font::Small }));
+        assert_eq!(read(&FixedGrid::new(3, 3, b'.'), b'#'), Ok(String::new()));
+    }
+
+    #[test]
+    fn test_read_unknown() {
+        let mut grid = draw(Font::Small, "CAB", 1);
+        grid.set(6, 3, b'.');
+        grid.set(12, 5, b'.');
+
+        let err = read(&grid, b'#').unwrap_err();
+        assert_eq!(err, OcrError::UnknownGlyphs { columns: vec![5..9, 10..14], partial: String::from("C??") });
+        assert_eq!(err.to_string(), "unrecognised glyphs in \"C??\" at columns 5..9, 10..14");
+    }
+}
@@ -0,0 +1,192 @@
+use std::ops::Range;
+use num::{Num, ToPrimitive};
+use crate::grid::FixedGrid;
+
+/// Running sums of a slice, which gives the sum of any range in O(1).
+#[derive(Clone, Debug)]
+pub struct PrefixSum<T> {
+    sums: Vec<T>,
+}
+
+impl<T> PrefixSum<T> where T: Num + Copy {
+    pub fn len(&self) -> usize {
+        self.sums.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the sum of the values in the range.
+    pub fn sum(&self, range: Range<usize>) -> T {
+        self.sums[range.end] - self.sums[range.start]
+    }
+
+    /// Get the sum of the first `n` values.
+    pub fn prefix(&self, n: usize) -> T {
+        self.sums[n]
+    }
+
+    /// Get the sum of the values from `start` to the end.
+    pub fn suffix(&self, start: usize) -> T {
+        self.total() - self.sums[start]
+    }
+
+    pub fn total(&self) -> T {
+        self.sums[self.len()]
+    }
+
+    pub fn new(values: &[T]) -> PrefixSum<T> {
+        let mut sums = Vec::with_capacity(values.len() + 1);
+        let mut sum = T::zero();
+        sums.push(sum);
+        for v in values.iter() {
+            sum = sum + *v;
+            sums.push(sum);
+        }
+
+        PrefixSum { sums }
+    }
+}
+
+/// Summed-area table of a grid, which gives the sum of any rectangle in O(1).
+#[derive(Clone)]
+pub struct PrefixSum2D<T> {
+    sums: FixedGrid<T>,
+}
+
+impl<T> PrefixSum2D<T> where T: Num + Copy {
+    pub fn width(&self) -> usize {
+        self.sums.width() - 1
+    }
+
+    pub fn height(&self) -> usize {
+        self.sums.height() - 1
+    }
+
+    /// Get the sum of the rectangle with the top left corner at (x, y). This panics if the
+    /// rectangle does not fit in the grid.
+    pub fn sum(&self, x: usize, y: usize, width: usize, height: usize) -> T {
+        let (x2, y2) = (x + width, y + height);
+        assert!(x2 <= self.width() && y2 <= self.height(), "Rectangle at ({}, {}) of {}x{} falls outside {}x{} grid", x, y, width, height, self.width(), self.height());
+
+        // Add before subtracting, so unsigned sums do not underflow.
+        self.sums[(x2, y2)] + self.sums[(x, y)] - self.sums[(x2, y)] - self.sums[(x, y2)]
+    }
+
+    /// Get the average of the rectangle, or `None` if it is empty. Like `sum`, this panics if
+    /// the rectangle does not fit in the grid.
+    pub fn average(&self, x: usize, y: usize, width: usize, height: usize) -> Option<f64> where T: ToPrimitive {
+        if width == 0 || height == 0 {
+            return None;
+        }
+
+        Some(self.sum(x, y, width, height).to_f64()? / (width * height) as f64)
+    }
+
+    /// Find the square of the size with the highest sum, and return its top left corner and
+    /// sum. Ties go to the first square in row-major order.
+    pub fn max_square(&self, size: usize) -> Option<((usize, usize), T)> where T: PartialOrd {
+        if size == 0 || size > self.width() || size > self.height() {
+            return None;
+        }
+
+        let mut best: Option<((usize, usize), T)> = None;
+        for y in 0..=(self.height() - size) {
+            for x in 0..=(self.width() - size) {
+                let sum = self.sum(x, y, size, size);
+                if best.is_none_or(|(_, best_sum)| sum > best_sum) {
+                    best = Some(((x, y), sum));
+                }
+            }
+        }
+
+        best
+    }
+
+    /// Find the square of any size with the highest sum, and return its top left corner, size
+    /// and sum. Ties go to the smallest size.
+    pub fn max_square_any(&self) -> Option<((usize, usize), usize, T)> where T: PartialOrd {
+        let mut best: Option<((usize, usize), usize, T)> = None;
+        for size in 1..=self.width().min(self.height()) {
+            let (pos, sum) = self.max_square(size)?;
+            if best.is_none_or(|(_, _, best_sum)| sum > best_sum) {
+                best = Some((pos, size, sum));
+            }
+        }
+
+        best
+    }
+
+    pub fn new(grid: &FixedGrid<T>) -> PrefixSum2D<T> {
+        let mut sums = FixedGrid::new(grid.width() + 1, grid.height() + 1, T::zero());
+        for y in 0..grid.height() {
+            let mut row_sum = T::zero();
+            for x in 0..grid.width() {
+                row_sum = row_sum + grid[(x, y)];
+                sums[(x + 1, y + 1)] = sums[(x + 1, y)] + row_sum;
+            }
+        }
+
+        PrefixSum2D { sums }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefix_sum() {
+        let sums = PrefixSum::new(&[3u32, 1, 4, 1, 5, 9, 2, 6]);
+
+        assert_eq!(sums.len(), 8);
+        assert_eq!(sums.total(), 31);
+        assert_eq!(sums.sum(2..5), 10);
+        assert_eq!(sums.sum(4..4), 0);
+        assert_eq!(sums.prefix(3), 8);
+        assert_eq!(sums.suffix(6), 8);
+        assert!(PrefixSum::<i64>::new(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_prefix_sum_2d() {
+        let grid = FixedGrid::from(4, 3, vec![
+            1u32, 2, 3, 4,
+            5, 6, 7, 8,
+            9, 10, 11, 12,
+        ]);
+        let sums = PrefixSum2D::new(&grid);
+
+        assert_eq!((sums.width(), sums.height()), (4, 3));
+        assert_eq!(sums.sum(0, 0, 4, 3), 78);
+        assert_eq!(sums.sum(1, 1, 2, 2), 34);
+        assert_eq!(sums.sum(3, 0, 1, 3), 24);
+        assert_eq!(sums.sum(2, 2, 0, 1), 0);
+        assert_eq!(sums.average(1, 1, 2, 2), Some(8.5));
+        assert_eq!(sums.average(1, 1, 0, 2), None);
+        assert_eq!(sums.max_square(2), Some(((2, 1), 38)));
+        assert_eq!(sums.max_square(4), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Rectangle at (3, 1) of 2x2 falls outside 4x3 grid")]
+    fn test_prefix_sum_2d_out_of_bounds() {
+        let sums = PrefixSum2D::new(&FixedGrid::new(4, 3, 1u32));
+        sums.sum(3, 1, 2, 2);
+    }
+
+    #[test]
+    fn test_max_square_fuel_cells() {
+        // Power levels of fuel cells with serial number 18, at 1-based positions.
+        let mut grid = FixedGrid::new(300, 300, 0i32);
+        for (x, y) in (0..300).flat_map(|y| (0..300).map(move |x| (x, y))) {
+            let rack = x as i32 + 11;
+            grid[(x, y)] = ((rack * (y as i32 + 1) + 18) * rack / 100) % 10 - 5;
+        }
+        let sums = PrefixSum2D::new(&grid);
+
+        assert_eq!(sums.max_square(3), Some(((32, 44), 29)));
+        assert_eq!(sums.max_square_any(), Some(((89, 268), 16, 113)));
+    }
+}
@@ -0,0 +1,204 @@
+use std::fmt::{self, Display, Formatter};
+use rustc_hash::FxHashMap;
+use termion::color;
+use crate::grid::Grid;
+
+/// Colour of an overlay layer, for terminals that support ANSI colours.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Colour {
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+}
+
+impl Colour {
+    fn ansi(&self) -> color::AnsiValue {
+        color::AnsiValue(match self {
+            Colour::Red => 1,
+            Colour::Green => 2,
+            Colour::Yellow => 3,
+            Colour::Blue => 4,
+            Colour::Magenta => 5,
+            Colour::Cyan => 6,
+            Colour::White => 7,
+        })
+    }
+}
+
+#[derive(Clone)]
+struct Layer {
+    cells: FxHashMap<(usize, usize), String>,
+    colour: Option<Colour>,
+}
+
+/// Renders a grid with layers of paths, highlights and labels drawn on top, for looking at
+/// search results. Later layers are drawn over earlier ones. When any label is wider than a
+/// char, every cell is padded to the widest one so the columns stay aligned.
+///
+/// The colours are only used `with_colour`, so the plain output can be compared in tests.
+#[derive(Clone)]
+pub struct Overlay<'a, G, F> {
+    grid: &'a G,
+    tile: F,
+    layers: Vec<Layer>,
+    colour: bool,
+}
+
+impl<'a, G, F> Overlay<'a, G, F> where G: Grid, F: Fn(&G::Item) -> char {
+    pub fn render(&self) -> String {
+        self.to_string()
+    }
+
+    pub fn print(&self) {
+        print!("{}", self);
+    }
+
+    /// Draw a route as arrows pointing to the next position, with `*` on the last position
+    /// and before jumps, like portals.
+    pub fn with_path(self, path: &[(usize, usize)], colour: Option<Colour>) -> Self {
+        let cells = path.iter().enumerate().map(|(i, (x, y))| {
+            let mark = match path.get(i + 1) {
+                Some((x2, y2)) => match (*x2 as isize - *x as isize, *y2 as isize - *y as isize) {
+                    (1, 0) => '>',
+                    (-1, 0) => '<',
+                    (0, 1) => 'v',
+                    (0, -1) => '^',
+                    (1, 1) | (-1, -1) => '\\',
+                    (1, -1) | (-1, 1) => '/',
+                    _ => '*',
+                },
+                None => '*',
+            };
+
+            ((*x, *y), mark)
+        });
+
+        self.with_layer(cells, colour)
+    }
+
+    /// Draw the same mark on every position.
+    pub fn with_highlights(self, positions: &[(usize, usize)], mark: char, colour: Option<Colour>) -> Self {
+        self.with_layer(positions.iter().map(|pos| (*pos, mark)), colour)
+    }
+
+    /// Draw a label on each position, like the distance from a start.
+    pub fn with_labels<L: Display>(self, labels: impl IntoIterator<Item=((usize, usize), L)>, colour: Option<Colour>) -> Self {
+        self.with_layer(labels, colour)
+    }
+
+    /// Use ANSI colours for the layers that have one.
+    pub fn with_colour(mut self, colour: bool) -> Self {
+        self.colour = colour;
+        self
+    }
+
+    fn with_layer<L: Display>(mut self, cells: impl IntoIterator<Item=((usize, usize), L)>, colour: Option<Colour>) -> Self {
+        let cells = cells.into_iter()
+            .filter(|((x, y), _)| self.grid.in_bounds(*x, *y))
+            .map(|(pos, label)| (pos, label.to_string()))
+            .collect();
+
+        self.layers.push(Layer { cells, colour });
+        self
+    }
+
+    /// Get the text on top at the position and the colour of its layer.
+    fn cell(&self, x: usize, y: usize) -> (String, Option<Colour>) {
+        for layer in self.layers.iter().rev() {
+            if let Some(label) = layer.cells.get(&(x, y)) {
+                return (label.clone(), layer.colour);
+            }
+        }
+
+        ((self.tile)(self.grid.get(x, y).unwrap()).to_string(), None)
+    }
+
+    pub fn new(grid: &'a G, tile: F) -> Overlay<'a, G, F> {
+        Overlay {
+            grid,
+            tile,
+            layers: Vec::new(),
+            colour: false,
+        }
+    }
+}
+
+impl<'a, G, F> Display for Overlay<'a, G, F> where G: Grid, F: Fn(&G::Item) -> char {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let width = self.layers.iter()
+            .flat_map(|layer| layer.cells.values())
+            .map(|label| label.chars().count())
+            .max()
+            .unwrap_or(1)
+            .max(1);
+
+        for y in 0..self.grid.height() {
+            for x in 0..self.grid.width() {
+                let (text, colour) = self.cell(x, y);
+                match colour {
+                    Some(colour) if self.colour => {
+                        write!(f, "{}{:>width$}{}", color::Fg(colour.ansi()), text, color::Fg(color::Reset), width = width)?;
+                    }
+                    _ => write!(f, "{:>width$}", text, width = width)?,
+                }
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::{BFS, BFSStep, FixedGrid};
+
+    const MAZE: &str = "
+#####
+#S..#
+##.##
+#..E#
+#####
+";
+
+    #[test]
+    fn test_path_overlay() {
+        let grid = FixedGrid::<u8>::from_str(MAZE);
+        let mut bfs = BFS::<()>::new().with_path();
+        bfs.run(&grid, 1, 1, false, |v, _, _| match *v {
+            b'#' => BFSStep::DeadEnd,
+            b'E' => BFSStep::Found(()),
+            _ => BFSStep::Continue(()),
+        });
+        let path = bfs.path().unwrap();
+
+        let overlay = Overlay::new(&grid, |v| *v as char)
+            .with_path(&path, Some(Colour::Green))
+            .with_highlights(&[(3, 2), (9, 9)], '!', Some(Colour::Red));
+
+        assert_eq!(overlay.render(), "#####\n#>v.#\n##v!#\n#.>*#\n#####\n");
+
+        let coloured = overlay.with_colour(true).render();
+        assert!(coloured.contains("\x1b[38;5;2m>\x1b[39m"));
+        assert!(coloured.contains("\x1b[38;5;1m!\x1b[39m"));
+        assert!(coloured.starts_with("#####\n#"));
+    }
+
+    #[test]
+    fn test_label_overlay() {
+        let grid = FixedGrid::<u8>::from_str(MAZE);
+        let labels = [((1, 1), 0), ((2, 1), 1), ((3, 1), 12)];
+
+        let overlay = Overlay::new(&grid, |v| if *v == b'#' { '#' } else { '.' })
+            .with_labels(labels, None)
+            .with_colour(true);
+
+        assert_eq!(overlay.render().lines().nth(1), Some(" # 0 112 #"));
+        assert_eq!(overlay.render().lines().nth(2), Some(" # # . # #"));
+    }
+}
@@ -0,0 +1,298 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, VecDeque};
+use std::hash::Hash;
+use radix_heap::RadixHeapMap;
+use rustc_hash::FxHashMap;
+
+/// Search struct for any hashable state. Like the grid searches, it keeps its allocations
+/// between runs.
+///
+/// The radix heap is used by default, which requires that the cost plus heuristic never goes
+/// below that of an already expanded state. Use `with_binary_heap` for negative costs or
+/// inconsistent heuristics.
+#[derive(Clone)]
+pub struct Search<S> {
+    costs: FxHashMap<S, i64>,
+    parents: Option<FxHashMap<S, S>>,
+    radix_heap: RadixHeapMap<Reverse<i64>, (i64, S)>,
+    binary_heap: BinaryHeap<SearchEntry<S>>,
+    queue: VecDeque<(S, i64)>,
+    use_binary_heap: bool,
+    found: Option<(S, i64)>,
+}
+
+impl<S> Search<S> where S: Hash + Eq + Clone {
+    /// Get the goal state and its cost from the last run.
+    pub fn found(&self) -> Option<(&S, i64)> {
+        self.found.as_ref().map(|(s, c)| (s, *c))
+    }
+
+    /// Get the cheapest known cost of a state reached in the last run.
+    pub fn cost_of(&self, state: &S) -> Option<i64> {
+        self.costs.get(state).copied()
+    }
+
+    /// Get the amount of distinct states reached in the last run.
+    pub fn visited_count(&self) -> usize {
+        self.costs.len()
+    }
+
+    /// Get the states from the start to the goal of the last run, including both. This is only
+    /// available when created `with_path`.
+    pub fn path(&self) -> Option<Vec<S>> {
+        self.path_to(&self.found.as_ref()?.0)
+    }
+
+    /// Get the cheapest route to any state reached in the last run.
+    pub fn path_to(&self, state: &S) -> Option<Vec<S>> {
+        let parents = self.parents.as_ref()?;
+        if !self.costs.contains_key(state) {
+            return None;
+        }
+
+        let mut path = vec![state.clone()];
+        let mut current = state;
+        while let Some(parent) = parents.get(current) {
+            path.push(parent.clone());
+            current = parent;
+        }
+
+        path.reverse();
+        Some(path)
+    }
+
+    /// Breadth-first search where every step costs 1. The returned cost is the amount of steps.
+    pub fn bfs<I>(&mut self, start: S, mut successors: impl FnMut(&S) -> I, mut goal: impl FnMut(&S) -> bool) -> Option<(S, i64)>
+        where I: IntoIterator<Item=S>
+    {
+        self.reset();
+        self.queue.clear();
+        self.costs.insert(start.clone(), 0);
+        self.queue.push_back((start, 0));
+
+        while let Some((state, steps)) = self.queue.pop_front() {
+            if goal(&state) {
+                self.found = Some((state.clone(), steps));
+                return Some((state, steps));
+            }
+
+            for next in successors(&state) {
+                if self.costs.contains_key(&next) {
+                    continue;
+                }
+
+                self.costs.insert(next.clone(), steps + 1);
+                if let Some(parents) = self.parents.as_mut() {
+                    parents.insert(next.clone(), state.clone());
+                }
+
+                self.queue.push_back((next, steps + 1));
+            }
+        }
+
+        None
+    }
+
+    /// Find the cheapest route to a goal state, where the successors come with the cost of
+    /// moving to them.
+    pub fn dijkstra<I>(&mut self, start: S, successors: impl FnMut(&S) -> I, goal: impl FnMut(&S) -> bool) -> Option<(S, i64)>
+        where I: IntoIterator<Item=(S, i64)>
+    {
+        self.astar(start, successors, |_| 0, goal)
+    }
+
+    /// Same as `dijkstra`, but ordered by cost plus the heuristic's estimate of the remaining cost.
+    pub fn astar<I>(&mut self, start: S, mut successors: impl FnMut(&S) -> I, mut heuristic: impl FnMut(&S) -> i64, mut goal: impl FnMut(&S) -> bool) -> Option<(S, i64)>
+        where I: IntoIterator<Item=(S, i64)>
+    {
+        self.reset();
+        self.radix_heap.clear();
+        self.binary_heap.clear();
+
+        let start_f = heuristic(&start);
+        self.costs.insert(start.clone(), 0);
+        self.push(start_f, 0, start);
+
+        while let Some((cost, state)) = self.pop() {
+            if self.costs.get(&state).map(|c| *c < cost).unwrap_or(false) {
+                continue;
+            }
+
+            if goal(&state) {
+                self.found = Some((state.clone(), cost));
+                return Some((state, cost));
+            }
+
+            for (next, step_cost) in successors(&state) {
+                let new_cost = cost + step_cost;
+                if self.costs.get(&next).map(|c| *c <= new_cost).unwrap_or(false) {
+                    continue;
+                }
+
+                self.costs.insert(next.clone(), new_cost);
+                if let Some(parents) = self.parents.as_mut() {
+                    parents.insert(next.clone(), state.clone());
+                }
+
+                let f = new_cost + heuristic(&next);
+                self.push(f, new_cost, next);
+            }
+        }
+
+        None
+    }
+
+    fn reset(&mut self) {
+        self.costs.clear();
+        if let Some(parents) = self.parents.as_mut() {
+            parents.clear();
+        }
+        self.found = None;
+    }
+
+    fn push(&mut self, f: i64, cost: i64, state: S) {
+        if self.use_binary_heap {
+            self.binary_heap.push(SearchEntry { f, cost, state });
+        } else {
+            self.radix_heap.push(Reverse(f), (cost, state));
+        }
+    }
+
+    fn pop(&mut self) -> Option<(i64, S)> {
+        if self.use_binary_heap {
+            self.binary_heap.pop().map(|e| (e.cost, e.state))
+        } else {
+            self.radix_heap.pop().map(|(_, v)| v)
+        }
+    }
+
+    /// Record the parent of every reached state, so that `path` and `path_to` can be used
+    /// after a run.
+    pub fn with_path(mut self) -> Search<S> {
+        self.parents = Some(FxHashMap::default());
+        self
+    }
+
+    /// Use a binary heap instead of the radix heap.
+    pub fn with_binary_heap(mut self) -> Search<S> {
+        self.use_binary_heap = true;
+        self
+    }
+
+    pub fn new() -> Search<S> {
+        Search {
+            costs: FxHashMap::default(),
+            parents: None,
+            radix_heap: RadixHeapMap::new(),
+            binary_heap: BinaryHeap::with_capacity(64),
+            queue: VecDeque::with_capacity(64),
+            use_binary_heap: false,
+            found: None,
+        }
+    }
+}
+
+impl<S> Default for Search<S> where S: Hash + Eq + Clone {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone)]
+struct SearchEntry<S> {
+    f: i64,
+    cost: i64,
+    state: S,
+}
+
+impl<S> PartialEq for SearchEntry<S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.f == other.f
+    }
+}
+
+impl<S> Eq for SearchEntry<S> {}
+
+impl<S> PartialOrd<Self> for SearchEntry<S> {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<S> Ord for SearchEntry<S> {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.f.cmp(&self.f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn number_steps(n: &u32) -> [u32; 2] {
+        [n + 1, n * 2]
+    }
+
+    #[test]
+    fn test_bfs() {
+        let mut search = Search::new().with_path();
+
+        let res = search.bfs(1u32, number_steps, |n| *n == 100);
+
+        assert_eq!(res, Some((100, 8)));
+        assert_eq!(search.path().unwrap(), vec![1, 2, 3, 6, 12, 24, 25, 50, 100]);
+    }
+
+    #[test]
+    fn test_dijkstra() {
+        // 0 -> 1 is cheap, but 1 -> 3 is expensive.
+        let edges: [&[(u32, i64)]; 4] = [
+            &[(1, 1), (2, 4)],
+            &[(3, 10)],
+            &[(3, 2)],
+            &[],
+        ];
+
+        for mut search in [Search::new().with_path(), Search::new().with_path().with_binary_heap()] {
+            let res = search.dijkstra(0u32, |n| edges[*n as usize].iter().copied(), |n| *n == 3);
+
+            assert_eq!(res, Some((3, 6)));
+            assert_eq!(search.path().unwrap(), vec![0, 2, 3]);
+            assert_eq!(search.cost_of(&1), Some(1));
+        }
+    }
+
+    #[test]
+    fn test_astar() {
+        let walls = [(2, 0), (2, 1), (2, 2), (2, 3)];
+        let successors = |(x, y): &(i64, i64)| {
+            [(1, 0), (-1, 0), (0, 1), (0, -1)].iter()
+                .map(|(xo, yo)| (x + xo, y + yo))
+                .filter(|(x, y)| (0..5).contains(x) && (0..5).contains(y) && !walls.contains(&(*x, *y)))
+                .map(|p| (p, 1))
+                .collect::<Vec<_>>()
+        };
+
+        let mut dijkstra = Search::new();
+        let mut astar = Search::new().with_path();
+        let res_dijkstra = dijkstra.dijkstra((0, 0), successors, |p| *p == (4, 0));
+        let res_astar = astar.astar((0, 0), successors, |(x, y)| (4 - x).abs() + y.abs(), |p| *p == (4, 0));
+
+        assert_eq!(res_dijkstra, Some(((4, 0), 12)));
+        assert_eq!(res_astar, Some(((4, 0), 12)));
+        assert!(astar.visited_count() <= dijkstra.visited_count());
+        assert_eq!(astar.path().unwrap().len(), 13);
+    }
+
+    #[test]
+    fn test_not_found() {
+        let mut search = Search::new();
+
+        assert_eq!(search.bfs(1u32, |n| if *n < 10 { vec![n + 1] } else { vec![] }, |n| *n == 20), None);
+        assert_eq!(search.visited_count(), 10);
+        assert!(search.found().is_none());
+        assert!(search.path().is_none());
+    }
+}
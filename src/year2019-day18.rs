@@ -1,5 +1,5 @@
 use common::aoc::{print_result, run_many, print_time_cold};
-use common::grid::{FixedGrid, BFS, BFSStep};
+use common::grid::FixedGrid;
 use rustc_hash::FxHashMap;
 
 const ALPHA: &[u8] = b"@abcdefghijklmnopqrstuvwxyz";
@@ -149,44 +149,11 @@ fn part2(maze: &FixedGrid<u8>) -> usize {
     shortest
 }
 
-fn search(maze: &FixedGrid<u8>, from_x: usize, from_y: usize, target: u8) -> (usize, u32, (usize, usize)) {
-    let mut bfs = BFS::<u32>::new();
-
-    if let Some((_, len, door_mask)) = bfs.run(&maze, from_x, from_y, false, |curr, _, door_mask| {
-        if *curr == target {
-            BFSStep::Found(*door_mask)
-        } else {
-            match *curr {
-                b'A'..=b'Z' => {
-                    let new_door_mask = *door_mask | (1 << ((*curr - b'A') + 1) as u32);
-                    BFSStep::Continue(new_door_mask)
-                }
-                b'a'..=b'z' => {
-                    BFSStep::Continue(*door_mask)
-                }
-                b'.' | b'@' => {
-                    BFSStep::Continue(*door_mask)
-                }
-                _ => {
-                    BFSStep::DeadEnd
-                }
-            }
-        }
-    }) {
-        (len, door_mask, bfs.found_pos().unwrap())
-    } else {
-        (0, 0, (0, 0))
-    }
-}
-
 struct MazeTree {
     keys: Vec<MTKey>,
 }
 
 struct MTKey {
-    x: usize,
-    y: usize,
-    quadrant: usize,
     paths: Vec<MTPath>,
 }
 
@@ -197,50 +164,40 @@ struct MTPath {
 }
 
 impl MazeTree {
+    /// Keys are indexed by their position in `ALPHA`, and every `@` is the same key 0. Each
+    /// key gets the shortest path to every other reachable key, in key order.
     fn build(maze: &FixedGrid<u8>) -> MazeTree {
-        let mut keys = Vec::new();
-
-        for i in 0..27 {
-            if let Some((x, y)) = maze.find(ALPHA[i]) {
-                keys.push(MTKey {
-                    x,
-                    y,
-                    quadrant: 0,
-                    paths: Vec::with_capacity(32),
-                })
-            } else {
-                break;
-            }
-        }
-
-        for i in 0..keys.len() {
-            for j in (i + 1)..keys.len() {
-                let (dist, doors, (x, y)) = search(&maze, keys[j].x, keys[j].y, ALPHA[i]);
-                if dist == 0 {
+        let graph = maze.point_graph(
+            |v| *v == b'@' || v.is_ascii_lowercase(),
+            |v| *v == b'.',
+            |v| v.is_ascii_uppercase(),
+        );
+        let key_index = |pos: (usize, usize)| ALPHA.iter().position(|c| *c == maze[pos]).unwrap();
+        let key_count = graph.points().iter().map(|pos| key_index(*pos) + 1).max().unwrap_or(0);
+
+        let mut shortest: Vec<Vec<Option<(usize, u32)>>> = vec![vec![None; key_count]; key_count];
+        for (from, pos) in graph.points().iter().enumerate() {
+            let i = key_index(*pos);
+            for path in graph.paths_from(from) {
+                let j = key_index(graph.points()[path.to]);
+                if i == j || shortest[i][j].is_some_and(|(len, _)| len <= path.len) {
                     continue;
                 }
 
-                if i == 0 {
-                    let bit_1 = if x == keys[0].x { 0 } else { 1 };
-                    let bit_2 = if y == keys[0].y { 0 } else { 2 };
-                    keys[j].quadrant = bit_1 | bit_2;
-                }
-
-                //println!("{} -> {} ({} {:#032b}) ({})", ALPHA[i] as char, ALPHA[j] as char, dist, doors, keys[j].quadrant);
-
-                keys[i].paths.push(MTPath {
-                    doors,
-                    to: j,
-                    len: dist,
-                });
-                keys[j].paths.push(MTPath {
-                    doors,
-                    to: i,
-                    len: dist,
-                });
+                let doors = path.gates.iter().fold(0, |mask, gate| mask | (1 << (maze[*gate] - b'A' + 1)));
+                shortest[i][j] = Some((path.len, doors));
             }
         }
 
+        let keys = shortest.into_iter()
+            .map(|row| MTKey {
+                paths: row.into_iter()
+                    .enumerate()
+                    .filter_map(|(to, path)| path.map(|(len, doors)| MTPath { to, len, doors }))
+                    .collect(),
+            })
+            .collect();
+
         MazeTree { keys }
     }
 }
@@ -307,16 +264,17 @@ mod tests {
 #############";
 
     #[test]
-    fn test_search() {
+    fn test_build() {
         let maze = FixedGrid::<u8>::from_str(SAMPLE_1A);
+        let tree = MazeTree::build(&maze);
 
-        let (sb_len, sb_doors, _) = search(&maze, 5, 1, b'b');
-        let (sa_len, sa_doors, _) = search(&maze, 5, 1, b'a');
+        let to_b = tree.keys[0].paths.iter().find(|p| p.to == 2).unwrap();
+        let to_a = tree.keys[0].paths.iter().find(|p| p.to == 1).unwrap();
 
-        assert_eq!(sb_doors, 2);
-        assert_eq!(sb_len, 4);
-        assert_eq!(sa_doors, 0);
-        assert_eq!(sa_len, 2);
+        assert_eq!(to_b.doors, 2);
+        assert_eq!(to_b.len, 4);
+        assert_eq!(to_a.doors, 0);
+        assert_eq!(to_a.len, 2);
     }
 
     #[test]
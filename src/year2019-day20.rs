@@ -1,5 +1,5 @@
 use common::aoc::{print_result, run_many, print_time_cold};
-use common::grid::{FixedGrid, BFS, BFSStep, manhattan_distance};
+use common::grid::{FixedGrid, BFS, BFSStep, Portal, manhattan_distance};
 use smallvec::{SmallVec, smallvec};
 
 fn main() {
@@ -20,15 +20,13 @@ fn main() {
 }
 
 fn part1(input: &Maze) -> usize {
-    let mut bfs = BFS::new();
+    let mut bfs = BFS::new().with_portals(input.portals.iter().map(|p| p.with_level_change(0)));
     let (sx, sy) = input.start_position;
     let res = bfs.run(&input.grid, sx, sy, false, |tile, _, _| {
         match tile {
             Tile::End => BFSStep::Found(()),
             Tile::Wall => BFSStep::DeadEnd,
             Tile::Floor => BFSStep::Continue(()),
-            Tile::WarpOuter(x, y, ..) => BFSStep::Warp(*x, *y, ()),
-            Tile::WarpInner(x, y, ..) => BFSStep::Warp(*x, *y, ()),
         }
     });
 
@@ -40,19 +38,13 @@ fn part1(input: &Maze) -> usize {
 }
 
 fn part2(input: &Maze) -> usize {
-    let mut bfs = BFS::new();
+    let mut bfs = BFS::new().with_portals(input.portals.iter().copied());
     let (sx, sy) = input.start_position;
-    let res = bfs.run_multilevel(&input.grid, sx, sy, 1000, false, |tile, pos, level, last_warp| {
-        if pos == *last_warp {
-            return BFSStep::DeadEnd;
-        }
-
+    let res = bfs.run_multilevel(&input.grid, sx, sy, 0, false, |tile, _, level, _| {
         match tile {
-            Tile::End => if level == 1000 {BFSStep::Found(*last_warp)} else {BFSStep::DeadEnd},
+            Tile::End => if level == 0 { BFSStep::Found(()) } else { BFSStep::DeadEnd },
             Tile::Wall => BFSStep::DeadEnd,
-            Tile::Floor => BFSStep::Continue(*last_warp),
-            Tile::WarpOuter(x, y, wx, wy) => BFSStep::WarpLevel(*x, *y, -1, (*wx, *wy)),
-            Tile::WarpInner(x, y, wx, wy) => BFSStep::WarpLevel(*x, *y, 1, (*wx, *wy)),
+            Tile::Floor => BFSStep::Continue(()),
         }
     });
 
@@ -65,6 +57,7 @@ fn part2(input: &Maze) -> usize {
 
 struct Maze {
     grid: FixedGrid<Tile>,
+    portals: Vec<Portal>,
     start_position: (usize, usize),
 }
 
@@ -72,46 +65,33 @@ struct Maze {
 enum Tile {
     Wall,
     Floor,
-    WarpInner(usize, usize, usize, usize),
-    WarpOuter(usize, usize, usize, usize),
     End,
 }
 
 impl Maze {
     #[allow(dead_code)]
     fn print(&self) {
-        let mut warps = Vec::with_capacity(32);
-
         println!("Start: {:?}", self.start_position);
-        for line in self.grid.lines() {
-            for tile in line.iter() {
+        for (y, line) in self.grid.lines().enumerate() {
+            for (x, tile) in line.iter().enumerate() {
                 match tile {
                     Tile::Wall => print!("#"),
-                    Tile::Floor => print!("."),
-                    Tile::WarpOuter(x, y, ..) => {
-                        warps.push((x, y));
-                        print!("^");
-                    },
-                    Tile::WarpInner(x, y, ..) => {
-                        warps.push((x, y));
-                        print!("V");
+                    Tile::Floor => match self.portals.iter().find(|p| p.from == (x, y)) {
+                        Some(p) if p.level_change < 0 => print!("^"),
+                        Some(_) => print!("V"),
+                        None => print!("."),
                     },
                     Tile::End => print!("E"),
                 }
             }
 
-            for (x, y) in warps.iter() {
-                print!(" {},{}", *x, *y);
-            }
-            warps.clear();
-
             println!();
         }
     }
 
     fn parse(input: &str) -> Maze {
         let raw_grid = FixedGrid::<u8>::from_str(input);
-        let mut warp_positions: Vec<([u8; 2], SmallVec<[(usize, usize); 4]>)> = Vec::with_capacity(64);
+        let mut warp_positions: Vec<([u8; 2], SmallVec<[(usize, usize); 2]>)> = Vec::with_capacity(64);
         let mut start_position = (0usize, 0usize);
         let mut grid = FixedGrid::new(raw_grid.width(), raw_grid.height(), Tile::Wall);
 
@@ -121,32 +101,27 @@ impl Maze {
             match *v {
                 b'A'..=b'Z' => {
                     let mut key = [*v, 0u8];
-                    let mut warp_pos = (0, 0);
                     let mut target_pos = (0, 0);
 
-                    if let Some(v2) = raw_grid.get(x + 1, y) {
+                    if let Some(v2) = raw_grid.get_safe(x + 1, y) {
                         if *v2 >= b'A' && *v2 <= b'Z' {
                             key[1] = *v2;
 
-                            if raw_grid.has(x - 1, y, b'.') {
+                            if raw_grid.has(x.wrapping_sub(1), y, b'.') {
                                 target_pos = (x - 1, y);
-                                warp_pos = (x, y);
                             } else {
                                 target_pos = (x + 2, y);
-                                warp_pos = (x + 1, y);
                             }
                         }
                     }
-                    if let Some(v2) = raw_grid.get(x, y + 1) {
+                    if let Some(v2) = raw_grid.get_safe(x, y + 1) {
                         if *v2 >= b'A' && *v2 <= b'Z' {
                             key[1] = *v2;
 
-                            if raw_grid.has(x, y - 1, b'.') {
+                            if raw_grid.has(x, y.wrapping_sub(1), b'.') {
                                 target_pos = (x, y - 1);
-                                warp_pos = (x, y);
                             } else {
                                 target_pos = (x, y + 2);
-                                warp_pos = (x, y + 1);
                             }
                         }
                     }
@@ -160,42 +135,42 @@ impl Maze {
                         }
                         b"ZZ" => {
                             grid[target_pos] = Tile::End;
-                            println!("{:?}", target_pos);
                         }
                         _ => {
                             if let Some((_, vec)) = warp_positions.iter_mut().find(|(k, _)| k == &key) {
                                 vec.push(target_pos);
-                                vec.push(warp_pos);
                             } else {
-                                warp_positions.push((key, smallvec![target_pos, warp_pos]));
+                                warp_positions.push((key, smallvec![target_pos]));
                             }
                         }
                     }
                 }
                 b'.' => {
-                    grid[(x, y)] = Tile::Floor;
+                    if grid[(x, y)] != Tile::End {
+                        grid[(x, y)] = Tile::Floor;
+                    }
                 }
                 _ => {}
             }
         }
 
+        let mut portals = Vec::with_capacity(warp_positions.len() * 2);
         for (_, list) in warp_positions.into_iter() {
-            assert_eq!(list.len(), 4);
-            let (ax, ay) = list[0];
-            let (bx, by) = list[1];
-            let (cx, cy) = list[2];
-            let (dx, dy) = list[3];
-            if manhattan_distance((ax, ay), middle) < manhattan_distance((cx, cy), middle) {
-                grid.set(bx, by, Tile::WarpInner(cx, cy, dx, dy));
-                grid.set(dx, dy, Tile::WarpOuter(ax, ay, bx, by));
+            assert_eq!(list.len(), 2);
+            let (a, b) = (list[0], list[1]);
+            let (inner, outer) = if manhattan_distance(a, middle) < manhattan_distance(b, middle) {
+                (a, b)
             } else {
-                grid.set(bx, by, Tile::WarpOuter(cx, cy, dx, dy));
-                grid.set(dx, dy, Tile::WarpInner(ax, ay, bx, by));
-            }
+                (b, a)
+            };
+
+            portals.push(Portal::new(inner, outer, 1).with_level_change(1));
+            portals.push(Portal::new(outer, inner, 1).with_level_change(-1));
         }
 
         Maze {
             grid,
+            portals,
             start_position,
         }
     }
@@ -267,13 +242,17 @@ FG..#########.....#--
 
     #[test]
     pub fn test_part1() {
-        //let mut maze_1 = Maze::parse(SAMPLE_1);
-        let mut maze_2 = Maze::parse(SAMPLE_2);
+        let maze_1 = Maze::parse(SAMPLE_1);
+        let maze_2 = Maze::parse(SAMPLE_2);
 
-        //maze_1.print();
-        maze_2.print();
-
-        //assert_eq!(part1(&maze_1), 23);
+        assert_eq!(part1(&maze_1), 23);
         assert_eq!(part1(&maze_2), 58);
     }
-}
\ No newline at end of file
+
+    #[test]
+    pub fn test_part2() {
+        let maze_1 = Maze::parse(SAMPLE_1);
+
+        assert_eq!(part2(&maze_1), 26);
+    }
+}